@@ -1,5 +1,10 @@
 use clap::{crate_authors, crate_description, crate_version, Parser};
-use nightgraphics::render::SvgRenderer;
+use nightgraphics::optimize::{dedupe, optimize, OptimizeSettings};
+use nightgraphics::render::{
+    GcodeRenderer, GcodeSettings, HpglRenderer, HpglSettings, PdfPages, PdfRenderer, PenLift,
+    PngRenderer, PngSettings, SvgRenderer,
+};
+use nightgraphics::units::{PaperSize, Unit, MM};
 use nightsketch::SketchSubcommand;
 use serde::{Deserialize, Serialize};
 
@@ -9,9 +14,129 @@ struct Opts {
     #[clap(subcommand)]
     sketch: SketchSubcommand,
 
-    /// Path where the resulting SVG file is stored
+    /// Path where the resulting drawing is stored. Paths ending in `.hpgl`
+    /// or `.plt` are written as HPGL, those ending in `.gcode`, `.gc` or
+    /// `.nc` as G-code, those ending in `.pdf` as PDF, those ending in `.png`
+    /// as PNG, and any others as SVG.
     #[clap(long, default_value = "drawing.svg")]
     output: String,
+
+    /// Physical unit for the SVG's width and height, overriding the one
+    /// chosen by the sketch
+    #[clap(long, possible_values = ["px", "mm", "cm", "in"])]
+    unit: Option<String>,
+
+    /// Remove lines that retrace earlier lines within this distance
+    #[clap(long)]
+    dedupe: Option<f64>,
+
+    /// Reorder, reverse and join strokes to minimize pen-up travel
+    #[clap(long)]
+    optimize: bool,
+
+    /// Distance within which stroke endpoints are joined when optimizing
+    #[clap(long, default_value = "0.1")]
+    merge_tolerance: f64,
+
+    /// Write each pen's layer to its own SVG, named after the output path
+    /// with the pen name appended
+    #[clap(long)]
+    split_pens: bool,
+
+    /// Paper loaded in the plotter, which HPGL output is centered on and
+    /// G-code output must fit within
+    #[clap(long, possible_values = ["a3", "a4", "letter", "tabloid", "postcard"])]
+    paper: Option<String>,
+
+    /// Turn HPGL output a quarter turn on the page
+    #[clap(long)]
+    plotter_rotate: bool,
+
+    /// Pen velocity for HPGL output, in cm/s
+    #[clap(long)]
+    velocity: Option<f64>,
+
+    /// Feed rate for G-code output, in mm/min
+    #[clap(long, default_value = "1000")]
+    feed_rate: f64,
+
+    /// Seconds to wait after lowering the pen in G-code output
+    #[clap(long, default_value = "0")]
+    dwell: f64,
+
+    /// Raise and lower the pen in G-code output with `M3 S<UP>` and
+    /// `M3 S<DOWN>` servo commands rather than Z moves
+    #[clap(long, number_of_values = 2, value_names = &["UP", "DOWN"])]
+    servo: Option<Vec<u32>>,
+
+    /// Write circles and ellipses as arcs in G-code output
+    #[clap(long)]
+    arcs: bool,
+
+    /// Split PDF output into a page per layer or per top-level sub-canvas
+    #[clap(long, possible_values = ["layer", "subcanvas"])]
+    pdf_pages: Option<String>,
+
+    /// Resolution of PNG output
+    #[clap(long, default_value = "96")]
+    dpi: f64,
+
+    /// Simulate ink bleed in PNG output by drawing strokes with this
+    /// opacity, so that overlapping strokes darken
+    #[clap(long)]
+    ink: Option<f64>,
+}
+
+impl Opts {
+    fn has_extension(&self, extensions: &[&str]) -> bool {
+        let output = self.output.to_lowercase();
+        extensions
+            .iter()
+            .any(|e| output.ends_with(&format!(".{}", e)))
+    }
+
+    fn paper(&self) -> Option<PaperSize> {
+        self.paper.as_ref().map(|p| p.parse().unwrap())
+    }
+
+    fn hpgl_settings(&self) -> HpglSettings {
+        HpglSettings {
+            rotate: self.plotter_rotate,
+            paper: self.paper(),
+            velocity: self.velocity,
+            ..Default::default()
+        }
+    }
+
+    fn pdf_pages(&self) -> PdfPages {
+        match self.pdf_pages.as_deref() {
+            Some("layer") => PdfPages::PerLayer,
+            Some("subcanvas") => PdfPages::PerSubcanvas,
+            _ => PdfPages::Single,
+        }
+    }
+
+    fn png_settings(&self) -> PngSettings {
+        PngSettings {
+            dpi: self.dpi,
+            ink: self.ink,
+            ..Default::default()
+        }
+    }
+
+    fn gcode_settings(&self) -> GcodeSettings {
+        GcodeSettings {
+            pen_lift: match self.servo.as_deref() {
+                Some(&[up, down]) => PenLift::Servo { up, down },
+                _ => GcodeSettings::default().pen_lift,
+            },
+            feed_rate: self.feed_rate,
+            dwell: self.dwell,
+            work_area: self.paper().map(|p| p.size() / MM),
+            arcs: self.arcs,
+            ..Default::default()
+        }
+    }
 }
 
 fn main() {
@@ -20,9 +145,51 @@ fn main() {
     // JSON serialization will eventually be used for config file
     // saving and loading
     println!("{}", opts_json);
-    let canvas = opts.sketch.exec();
-    match canvas {
+    let canvas = opts.sketch.exec().map(|mut c| {
+        if let Some(unit) = &opts.unit {
+            c.set_unit(unit.parse::<Unit>().unwrap());
+        }
+        c
+    });
+    let canvas = canvas.map(|c| match opts.dedupe {
+        Some(tolerance) => {
+            let (deduped, stats) = dedupe(&c, tolerance);
+            println!("Deduplicated lines: {}", stats);
+            deduped
+        }
+        None => c,
+    });
+    let canvas = canvas.map(|c| {
+        if opts.optimize {
+            let settings = OptimizeSettings {
+                merge_tolerance: opts.merge_tolerance,
+                ..Default::default()
+            };
+            let (optimized, stats) = optimize(&c, &settings);
+            println!("Optimized paths: {}", stats);
+            optimized
+        } else {
+            c
+        }
+    });
+    let rendered = match canvas {
+        Ok(c) if opts.has_extension(&["hpgl", "plt"]) => {
+            c.render_hpgl(&opts.output, &opts.hpgl_settings())
+        }
+        Ok(c) if opts.has_extension(&["gcode", "gc", "nc"]) => {
+            c.render_gcode(&opts.output, &opts.gcode_settings())
+        }
+        Ok(c) if opts.has_extension(&["pdf"]) => c.render_pdf(&opts.output, opts.pdf_pages()),
+        Ok(c) if opts.has_extension(&["png"]) => c.render_png(&opts.output, &opts.png_settings()),
+        Ok(c) if opts.split_pens => c.render_svg_per_pen(&opts.output),
         Ok(c) => c.render_svg(&opts.output),
-        Err(e) => println!("Error rendering sketch: {:?}", e),
+        Err(e) => {
+            println!("Error rendering sketch: {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = rendered {
+        eprintln!("Error saving {}: {}", opts.output, e);
+        std::process::exit(1);
     }
 }
@@ -35,5 +35,7 @@ fn main() {
         .unwrap();
     canvas.add(text);
 
-    canvas.render_svg("test_drawing.svg");
+    canvas
+        .render_svg("test_drawing.svg")
+        .expect("Unable to save SVG");
 }
@@ -1,9 +1,17 @@
-use crate::geometry::{Shape, Shaped};
-use crate::units::{Point, Size};
+use crate::geometry::{
+    split_subpaths, Affine, GridIndex, Path, Region, Shape, Shaped, DEFAULT_TOLERANCE,
+};
+use crate::pen::{Color, Pen};
+use crate::units::{PaperSize, Point, Size, Unit};
+use kurbo::Shape as KurboShape;
 
 pub enum CanvasElement {
     Canvas(Canvas),
-    Shape(Shape),
+    /// A shape along with the pen it is drawn with
+    Shape(Shape, Pen),
+    /// A shape along with the pen it is drawn with, whose closed subpaths
+    /// hide the parts of elements added before it that they cover
+    Opaque(Shape, Pen),
 }
 
 impl From<Canvas> for CanvasElement {
@@ -16,23 +24,41 @@ impl CanvasElement {}
 
 impl From<Shape> for CanvasElement {
     fn from(s: Shape) -> Self {
-        Self::Shape(s)
+        Self::Shape(s, Pen::default())
     }
 }
 impl<T: Shaped + Into<Shape> + Clone> From<T> for CanvasElement {
     fn from(s: T) -> CanvasElement {
-        Self::Shape(Shape::new(s))
+        Self::Shape(Shape::new(s), Pen::default())
     }
 }
 
+/// A collection of shapes and nested canvases sharing a coordinate frame.
+///
+/// Each canvas has its own coordinates, with `(0, 0)` at its top left
+/// corner, and a transform that places those coordinates within its parent
+/// when it is added to another canvas. Anything outside of an optional clip
+/// area, given in the canvas's own coordinates, is trimmed away when
+/// rendering.
+///
+/// Elements are drawn in the order they are added, and opaque shapes hide
+/// whatever was drawn before them underneath, as if cut from paper. Like
+/// clipping, this trims the hidden geometry away when rendering.
 pub struct Canvas {
     elements: Vec<CanvasElement>,
     origin: Point,
     size: Size,
     inner: kurbo::Rect,
+    transform: Affine,
+    clip: Option<Path>,
+    pens: Vec<Pen>,
+    unit: Unit,
+    occlusion: bool,
 }
 
 impl Canvas {
+    /// Creates a canvas of `size`, which is placed at `origin` when added to
+    /// another canvas
     pub fn new(origin: Point, size: Size) -> Self {
         Self {
             elements: vec![],
@@ -44,9 +70,22 @@ impl Canvas {
                 origin.x + size.width,
                 origin.y + size.height,
             ),
+            transform: Affine::translate(origin.to_vec2()),
+            clip: None,
+            pens: vec![],
+            unit: Unit::In,
+            occlusion: false,
         }
     }
 
+    /// Creates a canvas the size of `paper`, measured in the paper's own
+    /// unit when rendered
+    pub fn from_paper(paper: PaperSize) -> Self {
+        let mut canvas = Self::new(Point::ZERO, paper.size());
+        canvas.unit = paper.unit();
+        canvas
+    }
+
     pub fn width(&self) -> f64 {
         self.size.width
     }
@@ -57,25 +96,40 @@ impl Canvas {
         self.origin
     }
 
+    /// Returns the physical unit that the canvas's dimensions are given in
+    /// when rendered to a document
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+    pub fn set_unit(&mut self, unit: Unit) {
+        self.unit = unit;
+    }
+
+    /// Returns an empty canvas inset by `margin` on all sides, which shares
+    /// this canvas's registered pens.
+    ///
+    /// The new canvas's origin is relative to this canvas's top left corner,
+    /// since it is placed within this canvas when added to it, rather than
+    /// being this canvas's origin offset by `margin`. Its contents aren't
+    /// clipped, so call `clip_to_bounds` on it to trim anything that
+    /// shouldn't bleed into the margin.
     pub fn uniform_margin_subcanvas(&self, margin: f64) -> Self {
-        let origin = self.origin + (margin, margin);
         let size = self.size - Size::new(margin * 2., margin * 2.);
-        Self {
-            origin,
-            size,
-            elements: vec![],
-            inner: kurbo::Rect::new(
-                origin.x,
-                origin.y,
-                origin.x + size.width,
-                origin.y + size.height,
-            ),
-        }
+        let mut canvas = Canvas::new(Point::new(margin, margin), size);
+        canvas.pens = self.pens.clone();
+        canvas
     }
 
+    /// Divides this canvas into an `x` by `y` grid of empty canvases,
+    /// separated by `inner_margin`, ordered column by column and sharing this
+    /// canvas's registered pens.
+    ///
+    /// Like `uniform_margin_subcanvas`, each tile's origin is relative to
+    /// this canvas's top left corner, and its contents aren't clipped unless
+    /// `clip_to_bounds` is called on it.
     pub fn tiled_subcanvases(&self, x: u32, y: u32, inner_margin: f64) -> Vec<Self> {
-        let cell_width = self.size.width / x as f64 - inner_margin * (x - 1) as f64;
-        let cell_height = self.size.height / y as f64 - inner_margin * (y - 1) as f64;
+        let cell_width = (self.size.width - inner_margin * (x - 1) as f64) / x as f64;
+        let cell_height = (self.size.height - inner_margin * (y - 1) as f64) / y as f64;
         let cell_size = Size::new(cell_width, cell_height);
 
         let mut canvases = vec![];
@@ -86,7 +140,9 @@ impl Canvas {
                     i_x as f64 * (cell_width + inner_margin),
                     i_y as f64 * (cell_height + inner_margin),
                 );
-                canvases.push(Canvas::new(origin, cell_size));
+                let mut canvas = Canvas::new(origin, cell_size);
+                canvas.pens = self.pens.clone();
+                canvases.push(canvas);
             }
         }
         canvases
@@ -96,14 +152,563 @@ impl Canvas {
         kurbo::Rect::from_origin_size(self.origin, self.size)
     }
 
+    /// Adds a shape or sub-canvas. Shapes are drawn with the default pen.
     pub fn add<T: Into<CanvasElement>>(&mut self, component: T) {
         self.elements.push(component.into());
     }
 
+    /// Registers a pen with this canvas, so that shapes can be added to its
+    /// layer by name. Registering a pen with an existing name replaces it.
+    pub fn add_pen(&mut self, pen: Pen) {
+        match self.pens.iter_mut().find(|p| p.name == pen.name) {
+            Some(existing) => *existing = pen,
+            None => self.pens.push(pen),
+        }
+    }
+
+    /// Adds a shape to the named layer, drawn with the pen registered under
+    /// that name, or a black pen if none has been registered
+    pub fn add_to_layer<T: Into<Shape>>(&mut self, layer: &str, shape: T) {
+        let pen = self
+            .pens
+            .iter()
+            .find(|p| p.name == layer)
+            .cloned()
+            .unwrap_or_else(|| Pen::new(layer, Color::BLACK));
+        self.add_with_pen(pen, shape);
+    }
+
+    /// Adds a shape drawn with `pen`, without registering the pen
+    pub fn add_with_pen<T: Into<Shape>>(&mut self, pen: Pen, shape: T) {
+        self.elements.push(CanvasElement::Shape(shape.into(), pen));
+    }
+
+    /// Adds a shape drawn with the default pen, which hides the parts of
+    /// previously added elements that its closed subpaths cover
+    pub fn add_opaque<T: Into<Shape>>(&mut self, shape: T) {
+        self.add_opaque_with_pen(Pen::default(), shape);
+    }
+
+    /// Adds a shape drawn with `pen`, which hides the parts of previously
+    /// added elements that its closed subpaths cover
+    pub fn add_opaque_with_pen<T: Into<Shape>>(&mut self, pen: Pen, shape: T) {
+        self.elements.push(CanvasElement::Opaque(shape.into(), pen));
+    }
+
+    /// Returns whether every shape in this canvas and its sub-canvases is
+    /// treated as opaque
+    pub fn occlusion(&self) -> bool {
+        self.occlusion
+    }
+
+    /// Sets whether every shape in this canvas and its sub-canvases is
+    /// treated as opaque, so that closed shapes hide the parts of elements
+    /// added before them that they cover
+    pub fn set_occlusion(&mut self, occlusion: bool) {
+        self.occlusion = occlusion;
+    }
+
     pub fn elements(&self) -> &Vec<CanvasElement> {
         &self.elements
     }
     pub fn inner(&self) -> kurbo::Rect {
         self.inner
     }
+
+    /// Returns the canvas's extent in its own coordinates
+    pub fn bounds(&self) -> kurbo::Rect {
+        kurbo::Rect::from_origin_size(Point::ZERO, self.size)
+    }
+
+    /// Returns the transform from this canvas's coordinates to its parent's
+    pub fn transform(&self) -> Affine {
+        self.transform
+    }
+
+    /// Sets the transform from this canvas's coordinates to its parent's,
+    /// replacing the default translation to its origin. This has no effect on
+    /// a canvas that is rendered directly rather than added to another.
+    /// Like `Shape::transform`, it moves geometry but leaves stroke widths
+    /// as they are.
+    pub fn set_transform(&mut self, transform: Affine) {
+        self.transform = transform;
+    }
+
+    /// Returns the closed area, in this canvas's coordinates, outside of
+    /// which its contents are trimmed
+    pub fn clip(&self) -> Option<&Path> {
+        self.clip.as_ref()
+    }
+
+    /// Sets the rect, in this canvas's coordinates, outside of which its
+    /// contents are trimmed
+    pub fn set_clip(&mut self, clip: Option<kurbo::Rect>) {
+        self.clip = clip.map(|rect| Path::from(rect.to_path(DEFAULT_TOLERANCE)));
+    }
+
+    /// Trims the canvas's contents to the area that `mask` encloses under the
+    /// nonzero rule, in this canvas's coordinates
+    pub fn set_clip_mask(&mut self, mask: &dyn Shaped) {
+        self.clip = Some(mask.to_path());
+    }
+
+    /// Clips the canvas's contents to its own bounds
+    pub fn clip_to_bounds(&mut self) {
+        self.set_clip(Some(self.bounds()));
+    }
+
+    /// Returns a copy of the canvas in which every shape, including those in
+    /// sub-canvases, is trimmed to the clip areas of its own canvas and all
+    /// enclosing canvases, and to the parts not hidden by opaque shapes drawn
+    /// after it. Trimming produces real geometry rather than a mask, so the
+    /// copy is safe to plot and has no clips or opaque shapes of its own.
+    pub fn clipped(&self) -> Self {
+        let mut areas = vec![];
+        self.collect_occluders(None, Affine::default(), false, &mut 0, &mut areas);
+        self.clipped_within(None, Affine::default(), &Occluders::new(areas), &mut 0)
+    }
+
+    /// Combines the canvas's own clip with `outer`, an area given in the
+    /// canvas's own coordinates
+    fn clip_region(&self, outer: Option<Region>) -> Option<Region> {
+        let own = self
+            .clip
+            .as_ref()
+            .map(|c| Region::from_bezpath(&c.as_bezpath()));
+        match (outer, own) {
+            (Some(outer), Some(own)) => Some(outer.intersection(&own)),
+            (outer, own) => outer.or(own),
+        }
+    }
+
+    /// Returns the number of shapes in this canvas and its sub-canvases
+    fn shape_count(&self) -> usize {
+        self.elements
+            .iter()
+            .map(|element| match element {
+                CanvasElement::Canvas(c) => c.shape_count(),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Gathers the areas covered by opaque shapes, trimmed to the same clips
+    /// as `clipped_within` and mapped through `transform`. Each area is paired
+    /// with its shape's position in drawing order, counting on from `order`.
+    fn collect_occluders(
+        &self,
+        outer: Option<Region>,
+        transform: Affine,
+        opaque: bool,
+        order: &mut usize,
+        areas: &mut Vec<(usize, Region)>,
+    ) {
+        let clip = self.clip_region(outer);
+        let opaque = opaque || self.occlusion;
+        for element in &self.elements {
+            match element {
+                // A collapsed sub-canvas covers no area
+                CanvasElement::Canvas(c) if c.transform.determinant() == 0. => {
+                    *order += c.shape_count()
+                }
+                CanvasElement::Canvas(c) => {
+                    let inner_clip = clip
+                        .as_ref()
+                        .map(|region| region.transform(c.transform.inverse()));
+                    c.collect_occluders(inner_clip, transform * c.transform, opaque, order, areas);
+                }
+                CanvasElement::Shape(s, _) | CanvasElement::Opaque(s, _) => {
+                    if opaque || matches!(element, CanvasElement::Opaque(..)) {
+                        let (closed, _) = split_subpaths(&s.to_path().as_bezpath());
+                        let mut area = Region::from_bezpath(&closed);
+                        if let Some(region) = &clip {
+                            area = area.intersection(region);
+                        }
+                        if !area.is_empty() {
+                            areas.push((*order, area.transform(transform)));
+                        }
+                    }
+                    *order += 1;
+                }
+            }
+        }
+    }
+
+    /// Trims the canvas to its own clip and to `outer`, an area given in the
+    /// canvas's own coordinates, and hides the parts of shapes that are
+    /// covered by `occluders` once mapped through `transform`
+    fn clipped_within(
+        &self,
+        outer: Option<Region>,
+        transform: Affine,
+        occluders: &Occluders,
+        order: &mut usize,
+    ) -> Self {
+        let clip = self.clip_region(outer);
+        let mut canvas = Self {
+            elements: vec![],
+            origin: self.origin,
+            size: self.size,
+            inner: self.inner,
+            transform: self.transform,
+            clip: None,
+            pens: self.pens.clone(),
+            unit: self.unit,
+            occlusion: false,
+        };
+        for element in &self.elements {
+            match element {
+                CanvasElement::Canvas(c) => {
+                    let inner_clip = match &clip {
+                        // A collapsed sub-canvas can't contain anything inside a clip
+                        Some(_) if c.transform.determinant() == 0. => {
+                            *order += c.shape_count();
+                            continue;
+                        }
+                        Some(region) => Some(region.transform(c.transform.inverse())),
+                        None => None,
+                    };
+                    canvas.add(c.clipped_within(
+                        inner_clip,
+                        transform * c.transform,
+                        occluders,
+                        order,
+                    ));
+                }
+                CanvasElement::Shape(s, pen) | CanvasElement::Opaque(s, pen) => {
+                    let hidden = occluders.hiding(*order, s, transform);
+                    *order += 1;
+                    let visible = match &clip {
+                        Some(region) => region.clip_shape(s, true),
+                        None => Some(s.clone()),
+                    };
+                    let visible = match (visible, hidden) {
+                        (Some(s), Some(region)) => region.clip_shape(&s, false),
+                        (visible, _) => visible,
+                    };
+                    canvas
+                        .elements
+                        .extend(visible.map(|s| CanvasElement::Shape(s, pen.clone())));
+                }
+            }
+        }
+        canvas
+    }
+
+    /// Returns every shape in this canvas and its sub-canvases, trimmed to
+    /// all enclosing clip areas and mapped into this canvas's coordinates
+    pub fn shapes(&self) -> Vec<Shape> {
+        self.pen_shapes()
+            .into_iter()
+            .map(|(shape, _)| shape)
+            .collect()
+    }
+
+    /// Returns the shapes of `shapes`, grouped by the pen they are drawn with.
+    /// Pens that share a name but not a color get separate layers. Layers are
+    /// ordered by where their pen is first used.
+    pub fn layers(&self) -> Vec<(Pen, Vec<Shape>)> {
+        let mut layers: Vec<(Pen, Vec<Shape>)> = vec![];
+        for (shape, pen) in self.pen_shapes() {
+            match layers.iter_mut().find(|(p, _)| *p == pen) {
+                Some((_, layer)) => layer.push(shape),
+                None => layers.push((pen, vec![shape])),
+            }
+        }
+        layers
+    }
+
+    /// Returns every pen used by this canvas and its sub-canvases, one per
+    /// layer, in the order that they are first used
+    pub fn pens(&self) -> Vec<Pen> {
+        let mut pens: Vec<Pen> = vec![];
+        for element in &self.elements {
+            let used = match element {
+                CanvasElement::Canvas(c) => c.pens(),
+                CanvasElement::Shape(_, pen) | CanvasElement::Opaque(_, pen) => vec![pen.clone()],
+            };
+            for pen in used {
+                if !pens.contains(&pen) {
+                    pens.push(pen);
+                }
+            }
+        }
+        pens
+    }
+
+    fn pen_shapes(&self) -> Vec<(Shape, Pen)> {
+        let mut shapes = vec![];
+        self.clipped()
+            .collect_shapes(Affine::default(), &mut shapes);
+        shapes
+    }
+
+    fn collect_shapes(&self, transform: Affine, shapes: &mut Vec<(Shape, Pen)>) {
+        for element in &self.elements {
+            match element {
+                CanvasElement::Canvas(c) => c.collect_shapes(transform * c.transform, shapes),
+                CanvasElement::Shape(s, pen) | CanvasElement::Opaque(s, pen) => {
+                    shapes.push((s.transform(transform), pen.clone()))
+                }
+            }
+        }
+    }
+}
+
+/// The areas covered by opaque shapes, in the coordinates of the canvas being
+/// rendered, indexed by their bounds
+struct Occluders {
+    /// Each area, along with the position in drawing order of its shape
+    areas: Vec<(usize, Region)>,
+    bounds: Vec<(Point, Point)>,
+    index: GridIndex,
+}
+
+impl Occluders {
+    fn new(areas: Vec<(usize, Region)>) -> Self {
+        let bounds: Vec<(Point, Point)> = areas
+            .iter()
+            .map(|(_, area)| {
+                area.rings().iter().flatten().fold(
+                    (
+                        Point::new(f64::INFINITY, f64::INFINITY),
+                        Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+                    ),
+                    |(lo, hi), p| {
+                        (
+                            Point::new(lo.x.min(p.x), lo.y.min(p.y)),
+                            Point::new(hi.x.max(p.x), hi.y.max(p.y)),
+                        )
+                    },
+                )
+            })
+            .collect();
+        let index = GridIndex::new(&bounds);
+        Self {
+            areas,
+            bounds,
+            index,
+        }
+    }
+
+    /// Returns the area, in the coordinates that `transform` maps `shape`
+    /// from, that is covered by opaque shapes drawn after the `order`th
+    /// shape, or `None` if nothing covers it
+    fn hiding(&self, order: usize, shape: &Shape, transform: Affine) -> Option<Region> {
+        if self.areas.is_empty() || transform.determinant() == 0. {
+            return None;
+        }
+        let rect = transform.transform_rect_bbox(shape.to_path().bounding_box());
+        let (lo, hi) = (Point::new(rect.x0, rect.y0), Point::new(rect.x1, rect.y1));
+        // Each area winds once around the points it covers, so together they
+        // cover the union of their areas under the nonzero rule
+        let rings: Vec<Vec<Point>> = self
+            .index
+            .query(lo, hi)
+            .into_iter()
+            .filter(|&i| {
+                let (a, b) = self.bounds[i];
+                self.areas[i].0 > order && a.x <= hi.x && b.x >= lo.x && a.y <= hi.y && b.y >= lo.y
+            })
+            .flat_map(|i| self.areas[i].1.rings().to_vec())
+            .collect();
+        if rings.is_empty() {
+            None
+        } else {
+            Some(Region::new(rings).transform(transform.inverse()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line, Poly};
+
+    #[test]
+    fn tiles_fill_canvas() {
+        let canvas = Canvas::new(point(0, 0), Size::new(100., 50.));
+        let tiles = canvas.tiled_subcanvases(3, 2, 5.);
+        assert_eq!(tiles.len(), 6);
+        let last = tiles.last().unwrap().rect();
+        assert!((last.x1 - 100.).abs() < 1e-9 && (last.y1 - 50.).abs() < 1e-9);
+        assert!((tiles[2].origin().x - 35.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nested_frames() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        let mut sub = canvas.uniform_margin_subcanvas(10.);
+        assert!(sub.clip().is_none());
+        sub.clip_to_bounds();
+        sub.add(Circle::new(point(0, 0), 5.));
+        sub.add(Line::new(point(-20, 40), point(100, 40)).unwrap());
+        canvas.add(sub);
+
+        let shapes = canvas.shapes();
+        assert_eq!(shapes.len(), 2);
+
+        // The circle straddles the sub-canvas's corner, so only a quarter
+        // of it remains, moved by the margin
+        let arc = shapes[0].to_path().bounding_box();
+        assert!((arc.x0 - 10.).abs() < 1e-6 && (arc.y0 - 10.).abs() < 1e-6);
+        assert!((arc.x1 - 15.).abs() < 1e-2 && (arc.y1 - 15.).abs() < 1e-2);
+
+        let line = shapes[1].to_path().bounding_box();
+        assert!((line.x0 - 10.).abs() < 1e-6 && (line.x1 - 90.).abs() < 1e-6);
+        assert!((line.y0 - 50.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rotated_clip() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        let mut sub = Canvas::new(point(0, 0), Size::new(10., 10.));
+        sub.clip_to_bounds();
+        sub.set_transform(
+            Affine::translate((50., 50.)) * Affine::rotate(std::f64::consts::FRAC_PI_4),
+        );
+        sub.add(Line::new(point(-100, 5), point(100, 5)).unwrap());
+        canvas.add(sub);
+
+        let shapes = canvas.shapes();
+        assert_eq!(shapes.len(), 1);
+        assert!((shapes[0].to_path().perimeter() - 10.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mask_clip() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.set_clip_mask(&Circle::new(point(50, 50), 10.));
+        let mut sub = Canvas::new(point(40, 0), Size::new(100., 100.));
+        sub.add(Line::new(point(-40, 50), point(60, 50)).unwrap());
+        sub.add(Line::new(point(-40, 0), point(60, 0)).unwrap());
+        canvas.add(sub);
+        canvas.add(Circle::new(point(50, 50), 5.));
+
+        // Shapes wholly inside the mask are untouched, and those wholly
+        // outside are dropped
+        let clipped = canvas.clipped();
+        assert!(clipped.clip().is_none());
+        assert!(matches!(
+            clipped.elements()[1],
+            CanvasElement::Shape(Shape::Circle(_), _)
+        ));
+
+        let shapes = canvas.shapes();
+        assert_eq!(shapes.len(), 2);
+        let chord = shapes[0].to_path().bounding_box();
+        assert!((chord.width() - 20.).abs() < 1e-2);
+        assert!((chord.x0 - 40.).abs() < 1e-2 && (chord.y0 - 50.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn layers_by_pen() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add_pen(Pen::new("red", Color::RED));
+        canvas.add(Circle::new(point(10, 10), 5.));
+        canvas.add_to_layer("red", Circle::new(point(20, 10), 5.));
+        let mut sub = Canvas::new(point(50, 0), Size::new(50., 50.));
+        sub.add_to_layer("red", Circle::new(point(0, 0), 5.));
+        sub.add_to_layer("blue", Circle::new(point(0, 0), 5.));
+        canvas.add(sub);
+
+        let names: Vec<String> = canvas.pens().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["default", "red", "red", "blue"]);
+
+        // Pens are only looked up on the canvas a shape is added to, which
+        // here doesn't share its parent's pens, so the sub-canvas's red layer
+        // is drawn in black and kept apart from the red one
+        let layers = canvas.layers();
+        assert_eq!(layers.len(), 4);
+        assert_eq!(layers[1].0.color, Color::RED);
+        assert_eq!(layers[1].1.len(), 1);
+        assert_eq!(layers[2].0, Pen::new("red", Color::BLACK));
+        assert_eq!(layers[3].0.color, Color::BLACK);
+        assert_eq!(
+            canvas.uniform_margin_subcanvas(1.).pens,
+            vec![Pen::new("red", Color::RED)]
+        );
+    }
+
+    fn square(x: f64, y: f64, size: f64) -> Poly {
+        Poly::new(&[
+            point(x, y),
+            point(x + size, y),
+            point(x + size, y + size),
+            point(x, y + size),
+        ])
+        .unwrap()
+    }
+
+    fn total_length(shapes: &[Shape]) -> f64 {
+        shapes.iter().map(|s| s.to_path().perimeter()).sum()
+    }
+
+    #[test]
+    fn occlusion() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.set_occlusion(true);
+        canvas.add(Line::new(point(0, 50), point(100, 50)).unwrap());
+        canvas.add(square(40., 40., 20.));
+        // Open shapes and shapes added later are never hidden
+        canvas.add(Line::new(point(50, 0), point(50, 100)).unwrap());
+        canvas.add(Line::new(point(0, 45), point(100, 45)).unwrap());
+
+        let shapes = canvas.shapes();
+        assert_eq!(shapes.len(), 4);
+        assert!((shapes[0].to_path().perimeter() - 80.).abs() < 1e-6);
+        assert_eq!(shapes[0].to_path().separate().unwrap().len(), 2);
+        assert!(matches!(shapes[1], Shape::Poly(_)));
+        assert!((total_length(&shapes[2..]) - 200.).abs() < 1e-6);
+
+        // Closed shapes are hidden like any other, and wholly hidden shapes
+        // are dropped
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add(square(0., 0., 10.));
+        canvas.add(square(5., 5., 10.));
+        canvas.add_opaque(square(8., 8., 10.));
+        canvas.add_opaque(Circle::new(point(50, 50), 5.));
+        canvas.add_opaque(Circle::new(point(50, 50), 10.));
+        let shapes = canvas.shapes();
+        assert_eq!(shapes.len(), 4);
+        assert!((shapes[0].to_path().perimeter() - 36.).abs() < 1e-6);
+        assert!((shapes[1].to_path().perimeter() - 26.).abs() < 1e-6);
+        assert!(matches!(shapes[3], Shape::Circle(_)));
+    }
+
+    #[test]
+    fn nested_occlusion() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add(Line::new(point(0, 50), point(100, 50)).unwrap());
+
+        // Opaque shapes only hide what's within their canvas's clip, and
+        // hide shapes in the canvases around them
+        let mut sub = Canvas::new(point(20, 20), Size::new(10., 60.));
+        sub.clip_to_bounds();
+        sub.add_opaque(square(-20., 0., 60.));
+        sub.add(Line::new(point(-20, 40), point(40, 40)).unwrap());
+        canvas.add(sub);
+
+        let mut sub = Canvas::new(point(60, 0), Size::new(40., 100.));
+        sub.set_occlusion(true);
+        sub.add(square(0., 45., 10.));
+        canvas.add(sub);
+        canvas.add_opaque(Circle::new(point(25, 60), 2.));
+
+        let layers = canvas.layers();
+        assert_eq!(layers.len(), 1);
+        let shapes = &layers[0].1;
+        assert_eq!(shapes.len(), 5);
+        assert!((shapes[0].to_path().perimeter() - 80.).abs() < 1e-6);
+        assert_eq!(shapes[0].to_path().separate().unwrap().len(), 3);
+        assert!((shapes[2].to_path().perimeter() - 6.).abs() < 1e-2);
+
+        // Every renderer works from the trimmed shapes
+        let clipped = canvas.clipped();
+        let sub = match &clipped.elements()[1] {
+            CanvasElement::Canvas(c) => c,
+            _ => panic!("expected a sub-canvas"),
+        };
+        assert!(matches!(sub.elements()[0], CanvasElement::Shape(..)));
+    }
 }
@@ -0,0 +1,656 @@
+use super::region::{BandIndex, GridIndex};
+use super::{Path, Point, Vec2, DEFAULT_TOLERANCE};
+use kurbo::{flatten, BezPath, PathEl};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+/// Points are snapped to a grid of this size before any intersection tests so
+/// that vertices shared between operands compare exactly equal.
+const SNAP_GRID: f64 = 1e-6;
+
+/// Distance under which a vertex is considered to lie on another edge.
+const EPSILON: f64 = 1e-5;
+
+/// The set operations that can be performed between two regions.
+///
+/// Regions are evaluated with the nonzero fill rule, matching
+/// `Shaped::contains`. Open subpaths are treated as if they were closed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BooleanOp {
+    /// Everything covered by either operand
+    Union,
+    /// Only what is covered by both operands
+    Intersection,
+    /// What is covered by the first operand but not the second
+    Difference,
+    /// What is covered by exactly one of the operands
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            Self::Union => in_a || in_b,
+            Self::Intersection => in_a && in_b,
+            Self::Difference => in_a && !in_b,
+            Self::Xor => in_a != in_b,
+        }
+    }
+}
+
+type Key = (i64, i64);
+
+fn snap(p: Point) -> Point {
+    Point::new(
+        (p.x / SNAP_GRID).round() * SNAP_GRID,
+        (p.y / SNAP_GRID).round() * SNAP_GRID,
+    )
+}
+
+fn key(p: Point) -> Key {
+    (
+        (p.x / SNAP_GRID).round() as i64,
+        (p.y / SNAP_GRID).round() as i64,
+    )
+}
+
+/// Flattens a path into closed rings of snapped points, dropping degenerate
+/// rings and repeated vertices.
+pub(crate) fn rings(path: &BezPath) -> Vec<Vec<Point>> {
+    let mut path_elements = vec![];
+    let callback = |el: PathEl| path_elements.push(el);
+    flatten(path.iter(), DEFAULT_TOLERANCE, callback);
+
+    let mut rings = vec![];
+    let mut ring: Vec<Point> = vec![];
+    let mut finish = |ring: &mut Vec<Point>| {
+        if ring.len() > 1 && key(ring[0]) == key(ring[ring.len() - 1]) {
+            ring.pop();
+        }
+        if ring.len() >= 3 {
+            rings.push(std::mem::take(ring));
+        } else {
+            ring.clear();
+        }
+    };
+
+    for el in path_elements {
+        match el {
+            PathEl::MoveTo(p) => {
+                finish(&mut ring);
+                ring.push(snap(p));
+            }
+            PathEl::LineTo(p) => {
+                let p = snap(p);
+                if ring.last().map(|l| key(*l)) != Some(key(p)) {
+                    ring.push(p);
+                }
+            }
+            PathEl::ClosePath => finish(&mut ring),
+            _ => unreachable!(),
+        }
+    }
+    finish(&mut ring);
+
+    rings
+}
+
+/// Converts a set of rings back into a closed, flattened `Path`
+pub(crate) fn rings_to_path(rings: &[Vec<Point>]) -> Path {
+    let mut bez = BezPath::new();
+    for ring in rings {
+        bez.move_to(ring[0]);
+        for p in ring.iter().skip(1) {
+            bez.line_to(*p);
+        }
+        bez.close_path();
+    }
+    bez.into()
+}
+
+#[derive(Copy, Clone)]
+struct Edge {
+    a: Point,
+    b: Point,
+}
+
+impl Edge {
+    fn min_x(&self) -> f64 {
+        self.a.x.min(self.b.x)
+    }
+    fn max_x(&self) -> f64 {
+        self.a.x.max(self.b.x)
+    }
+    fn min_y(&self) -> f64 {
+        self.a.y.min(self.b.y)
+    }
+    fn max_y(&self) -> f64 {
+        self.a.y.max(self.b.y)
+    }
+
+    /// Returns `p` if it lies on the interior of this edge, within `EPSILON`
+    fn interior_point(&self, p: Point) -> Option<Point> {
+        let r = self.b - self.a;
+        let len2 = r.hypot2();
+        if len2 == 0. {
+            return None;
+        }
+        let t = (p - self.a).dot(r) / len2;
+        if t <= 0. || t >= 1. {
+            return None;
+        }
+        if (p - (self.a + r * t)).hypot() < EPSILON {
+            Some(p)
+        } else {
+            None
+        }
+    }
+}
+
+/// An edge of the planar arrangement of both operands, along with the net
+/// number of times each operand's boundary runs along it from `a` to `b`.
+struct ArrangedEdge {
+    a: Point,
+    b: Point,
+    winding: [i32; 2],
+}
+
+/// Finds the points at which `e` and `f` must be split so that the two no
+/// longer cross or overlap other than at shared endpoints.
+fn split_points(e: &Edge, f: &Edge) -> (Vec<Point>, Vec<Point>) {
+    let mut e_splits = vec![];
+    let mut f_splits = vec![];
+
+    // Touching and collinear overlapping edges are handled by splitting each
+    // edge at any endpoint of the other that lies along it
+    for p in [f.a, f.b] {
+        e_splits.extend(e.interior_point(p));
+    }
+    for p in [e.a, e.b] {
+        f_splits.extend(f.interior_point(p));
+    }
+
+    let r = e.b - e.a;
+    let s = f.b - f.a;
+    let denom = r.cross(s);
+    if denom != 0. {
+        let qp = f.a - e.a;
+        let t = qp.cross(s) / denom;
+        let u = qp.cross(r) / denom;
+        if t > 0. && t < 1. && u > 0. && u < 1. {
+            let p = snap(e.a + r * t);
+            e_splits.push(p);
+            f_splits.push(p);
+        }
+    }
+
+    (e_splits, f_splits)
+}
+
+/// Builds the arrangement of all edges in `a` and `b`: every edge is split at
+/// every intersection (including self-intersections), and coincident pieces
+/// are merged into a single edge carrying the winding of all its sources.
+fn arrange(a: &[Vec<Point>], b: &[Vec<Point>]) -> Vec<ArrangedEdge> {
+    let mut edges = vec![];
+    let mut owners = vec![];
+    for (owner, rings) in [a, b].iter().enumerate() {
+        for ring in rings.iter() {
+            for i in 0..ring.len() {
+                edges.push(Edge {
+                    a: ring[i],
+                    b: ring[(i + 1) % ring.len()],
+                });
+                owners.push(owner);
+            }
+        }
+    }
+
+    let mut splits: Vec<Vec<Point>> = edges.iter().map(|e| vec![e.a, e.b]).collect();
+
+    let bounds: Vec<(Point, Point)> = edges
+        .iter()
+        .map(|e| {
+            (
+                Point::new(e.min_x() - EPSILON, e.min_y() - EPSILON),
+                Point::new(e.max_x() + EPSILON, e.max_y() + EPSILON),
+            )
+        })
+        .collect();
+    let grid = GridIndex::new(&bounds);
+    for (i, j) in grid.candidate_pairs(&bounds) {
+        let (e, f) = (edges[i], edges[j]);
+        if f.min_x() > e.max_x() + EPSILON
+            || f.max_x() < e.min_x() - EPSILON
+            || f.min_y() > e.max_y() + EPSILON
+            || f.max_y() < e.min_y() - EPSILON
+        {
+            continue;
+        }
+        let (e_splits, f_splits) = split_points(&e, &f);
+        splits[i].extend(e_splits);
+        splits[j].extend(f_splits);
+    }
+
+    // Intersections are computed per pair of edges, so edges that coincide
+    // may each have been split at slightly different points. Sharing every
+    // split point with all edges passing through it keeps them identical.
+    let mut points: Vec<Point> = vec![];
+    let mut seen = HashSet::new();
+    for p in splits.iter().flatten() {
+        if seen.insert(key(*p)) {
+            points.push(*p);
+        }
+    }
+    let mut point_cells: Vec<Vec<Point>> = vec![vec![]; grid.cells_len()];
+    for p in points {
+        point_cells[grid.cell(p)].push(p);
+    }
+    for (bound, (edge, edge_splits)) in bounds.iter().zip(edges.iter().zip(splits.iter_mut())) {
+        for cell in grid.cells_overlapping(bound.0, bound.1) {
+            for p in &point_cells[cell] {
+                edge_splits.extend(edge.interior_point(*p));
+            }
+        }
+    }
+
+    let mut indices: HashMap<(Key, Key), usize> = HashMap::new();
+    let mut arranged: Vec<ArrangedEdge> = vec![];
+    for ((edge, owner), mut points) in edges.iter().zip(owners).zip(splits) {
+        let dir = edge.b - edge.a;
+        points.sort_by(|p, q| {
+            (*p - edge.a)
+                .dot(dir)
+                .partial_cmp(&(*q - edge.a).dot(dir))
+                .unwrap()
+        });
+        for (p, q) in points.iter().zip(points.iter().skip(1)) {
+            let (kp, kq) = (key(*p), key(*q));
+            if kp == kq {
+                continue;
+            }
+            let (canonical, sign) = if kp < kq {
+                ((kp, kq), 1)
+            } else {
+                ((kq, kp), -1)
+            };
+            let idx = *indices.entry(canonical).or_insert_with(|| {
+                let (a, b) = if sign == 1 { (*p, *q) } else { (*q, *p) };
+                arranged.push(ArrangedEdge {
+                    a,
+                    b,
+                    winding: [0, 0],
+                });
+                arranged.len() - 1
+            });
+            arranged[idx].winding[owner] += sign;
+        }
+    }
+
+    arranged.retain(|e| e.winding != [0, 0]);
+    arranged
+}
+
+/// Computes the winding numbers of both operands on the left and right of
+/// every arranged edge, returned as `[left, right]` for each.
+///
+/// The faces of the arrangement are traced from half-edges, so that winding
+/// only needs to be found by ray casting once per connected group of edges,
+/// and can then be carried across each edge from one face to the next.
+fn face_windings(edges: &[ArrangedEdge]) -> Vec<[[i32; 2]; 2]> {
+    // Half-edge `2 * i` runs along edge `i` from `a` to `b`, and `2 * i + 1`
+    // runs back from `b` to `a`
+    let mut vertex_ids: HashMap<Key, usize> = HashMap::new();
+    let mut vertices: Vec<Point> = vec![];
+    let mut origins = Vec::with_capacity(edges.len() * 2);
+    for e in edges {
+        for p in [e.a, e.b] {
+            let id = *vertex_ids.entry(key(p)).or_insert_with(|| {
+                vertices.push(p);
+                vertices.len() - 1
+            });
+            origins.push(id);
+        }
+    }
+    let half_edge_dir = |h: usize| {
+        let e = &edges[h / 2];
+        if h & 1 == 0 {
+            e.b - e.a
+        } else {
+            e.a - e.b
+        }
+    };
+
+    // Outgoing half-edges around each vertex, in counter-clockwise order
+    let mut outgoing: Vec<Vec<usize>> = vec![vec![]; vertices.len()];
+    for (h, &v) in origins.iter().enumerate() {
+        outgoing[v].push(h);
+    }
+    let mut position = vec![0; origins.len()];
+    for around in &mut outgoing {
+        around.sort_by(|&g, &h| {
+            half_edge_dir(g)
+                .atan2()
+                .partial_cmp(&half_edge_dir(h).atan2())
+                .unwrap()
+        });
+        for (n, &h) in around.iter().enumerate() {
+            position[h] = n;
+        }
+    }
+
+    // The face on the left of a half-edge continues along the next outgoing
+    // half-edge clockwise from its twin
+    let next = |h: usize| {
+        let twin = h ^ 1;
+        let around = &outgoing[origins[twin]];
+        around[(position[twin] + around.len() - 1) % around.len()]
+    };
+    let mut face_of = vec![usize::MAX; origins.len()];
+    let mut face_count = 0;
+    for start in 0..origins.len() {
+        if face_of[start] != usize::MAX {
+            continue;
+        }
+        let mut h = start;
+        while face_of[h] == usize::MAX {
+            face_of[h] = face_count;
+            h = next(h);
+        }
+        face_count += 1;
+    }
+
+    let index = BandIndex::new(
+        &edges
+            .iter()
+            .map(|e| (e.a.y.min(e.b.y), e.a.y.max(e.b.y)))
+            .collect::<Vec<_>>(),
+    );
+    let mut component_of = vec![usize::MAX; edges.len()];
+    let mut windings: Vec<Option<[i32; 2]>> = vec![None; face_count];
+    let mut face_half_edges: Vec<Vec<usize>> = vec![vec![]; face_count];
+    for (h, &face) in face_of.iter().enumerate() {
+        face_half_edges[face].push(h);
+    }
+
+    for seed in 0..face_count {
+        if windings[seed].is_some() {
+            continue;
+        }
+
+        // Find winding relative to the seed face across the whole connected
+        // group, crossing from the left of each edge to its right
+        let component = seed;
+        let mut relative: Vec<(usize, [i32; 2])> = vec![(seed, [0, 0])];
+        let mut seen = HashMap::new();
+        seen.insert(seed, [0, 0]);
+        let mut queue = vec![seed];
+        while let Some(face) = queue.pop() {
+            let w = seen[&face];
+            for &h in &face_half_edges[face] {
+                let e = &edges[h / 2];
+                component_of[h / 2] = component;
+                let sign = if h & 1 == 0 { -1 } else { 1 };
+                let across = face_of[h ^ 1];
+                if let Entry::Vacant(entry) = seen.entry(across) {
+                    let w_across = [w[0] + sign * e.winding[0], w[1] + sign * e.winding[1]];
+                    entry.insert(w_across);
+                    relative.push((across, w_across));
+                    queue.push(across);
+                }
+            }
+        }
+
+        // The group's leftmost vertex looks out onto its outer face, between
+        // its outgoing half-edges that turn furthest counter-clockwise and
+        // furthest clockwise
+        let leftmost = relative
+            .iter()
+            .flat_map(|(face, _)| face_half_edges[*face].iter())
+            .map(|&h| origins[h])
+            .min_by(|u, v| {
+                let (p, q) = (vertices[*u], vertices[*v]);
+                (p.x, p.y).partial_cmp(&(q.x, q.y)).unwrap()
+            })
+            .unwrap();
+        let outer_half_edge = *outgoing[leftmost].last().unwrap();
+        let outer_face = face_of[outer_half_edge];
+
+        // Cast a ray in -x from there, counting only other groups' edges
+        let p = vertices[leftmost];
+        let mut outside = [0, 0];
+        for &i in index.query(p.y) {
+            if component_of[i] == component {
+                continue;
+            }
+            let e = &edges[i];
+            if (e.a.y <= p.y) != (e.b.y <= p.y) {
+                let x = e.a.x + (p.y - e.a.y) * (e.b.x - e.a.x) / (e.b.y - e.a.y);
+                if x < p.x {
+                    let dir = if e.b.y < e.a.y { 1 } else { -1 };
+                    outside[0] += dir * e.winding[0];
+                    outside[1] += dir * e.winding[1];
+                }
+            }
+        }
+
+        let offset = [
+            outside[0] - seen[&outer_face][0],
+            outside[1] - seen[&outer_face][1],
+        ];
+        for (face, w) in relative {
+            windings[face] = Some([w[0] + offset[0], w[1] + offset[1]]);
+        }
+    }
+
+    (0..edges.len())
+        .map(|i| {
+            [
+                windings[face_of[2 * i]].unwrap(),
+                windings[face_of[2 * i + 1]].unwrap(),
+            ]
+        })
+        .collect()
+}
+
+/// Chains directed boundary edges into closed rings, preferring the leftmost
+/// turn at vertices shared by several rings so that they stay separate.
+fn link(edges: &[Edge]) -> Vec<Vec<Point>> {
+    let mut outgoing: HashMap<Key, Vec<usize>> = HashMap::new();
+    for (i, e) in edges.iter().enumerate() {
+        outgoing.entry(key(e.a)).or_default().push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut rings = vec![];
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let start_key = key(edges[start].a);
+        let mut ring = vec![edges[start].a];
+        let mut current = start;
+        loop {
+            let e = edges[current];
+            if key(e.b) == start_key {
+                break;
+            }
+            ring.push(e.b);
+            let incoming = e.b - e.a;
+            let next = outgoing
+                .get(&key(e.b))
+                .into_iter()
+                .flatten()
+                .filter(|&&i| !used[i])
+                .map(|&i| {
+                    let out = edges[i].b - edges[i].a;
+                    (i, incoming.cross(out).atan2(incoming.dot(out)))
+                })
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            match next {
+                Some((i, _)) => {
+                    used[i] = true;
+                    current = i;
+                }
+                None => break,
+            }
+        }
+        let ring = remove_collinear(ring);
+        if ring.len() >= 3 {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// Drops vertices that lie on a straight run between their neighbours, which
+/// are left behind wherever an edge was split but did not change direction.
+fn remove_collinear(ring: Vec<Point>) -> Vec<Point> {
+    let is_straight = |prev: Point, p: Point, next: Point| {
+        let (d0, d1): (Vec2, Vec2) = (p - prev, next - p);
+        d0.cross(d1).abs() <= 1e-9 * d0.hypot() * d1.hypot() && d0.dot(d1) > 0.
+    };
+
+    let mut ring = ring;
+    let mut changed = true;
+    while changed && ring.len() >= 3 {
+        changed = false;
+        let mut kept = Vec::with_capacity(ring.len());
+        let n = ring.len();
+        for i in 0..n {
+            let prev = kept.last().copied().unwrap_or(ring[(i + n - 1) % n]);
+            if is_straight(prev, ring[i], ring[(i + 1) % n]) {
+                changed = true;
+            } else {
+                kept.push(ring[i]);
+            }
+        }
+        ring = kept;
+    }
+    ring
+}
+
+/// Performs `op` between two sets of rings, returning the boundary of the
+/// resulting region as rings with the filled side on their left, so outer
+/// boundaries have positive area and holes negative area.
+pub(crate) fn boolean_rings(a: &[Vec<Point>], b: &[Vec<Point>], op: BooleanOp) -> Vec<Vec<Point>> {
+    resolve_rings(a, b, |w_a, w_b| op.apply(w_a != 0, w_b != 0))
+}
+
+/// Like `boolean_rings`, but decides which areas are filled directly from
+/// the winding numbers of `a` and `b` around them.
+pub(crate) fn resolve_rings(
+    a: &[Vec<Point>],
+    b: &[Vec<Point>],
+    inside: impl Fn(i32, i32) -> bool,
+) -> Vec<Vec<Point>> {
+    let arranged = arrange(a, b);
+    if arranged.is_empty() {
+        return vec![];
+    }
+
+    let mut boundary = vec![];
+    for (e, [left, right]) in arranged.iter().zip(face_windings(&arranged)) {
+        let inside_left = inside(left[0], left[1]);
+        if inside_left == inside(right[0], right[1]) {
+            continue;
+        }
+        boundary.push(if inside_left {
+            Edge { a: e.a, b: e.b }
+        } else {
+            Edge { a: e.b, b: e.a }
+        });
+    }
+
+    link(&boundary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Poly, Shaped};
+
+    fn square(x: f64, y: f64, size: f64) -> Poly {
+        Poly::new(&[
+            point(x, y),
+            point(x + size, y),
+            point(x + size, y + size),
+            point(x, y + size),
+        ])
+        .unwrap()
+    }
+
+    fn assert_area(path: &Path, expected: f64) {
+        assert!(
+            (path.area() - expected).abs() < 1e-6,
+            "Expected area: {}, Actual area: {}",
+            expected,
+            path.area()
+        );
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let a = square(0., 0., 10.);
+        let b = square(5., 5., 10.);
+
+        assert_area(&a.union(&b), 175.);
+        assert_area(&a.intersection(&b), 25.);
+        assert_area(&a.xor(&b), 150.);
+        assert_area(&a.boolean(&b, BooleanOp::Difference), 75.);
+    }
+
+    #[test]
+    fn coincident_edges() {
+        let a = square(0., 0., 10.);
+        let b = square(10., 0., 10.);
+
+        let union = a.union(&b);
+        assert_area(&union, 200.);
+        // The shared edge disappears, leaving a single rectangle
+        assert_eq!(union.separate().unwrap().len(), 1);
+        assert_eq!(union.commands().len(), 5);
+
+        assert!(a.intersection(&b).commands().is_empty());
+        assert_area(&a.union(&a), 100.);
+        assert_area(&a.xor(&a), 0.);
+    }
+
+    #[test]
+    fn holes() {
+        let mut framed = square(0., 0., 30.).to_path();
+        // Reversed winding makes an inner hole
+        framed.move_to(point(10, 10));
+        framed.line_to(point(10, 20));
+        framed.line_to(point(20, 20));
+        framed.line_to(point(20, 10));
+        framed.close();
+        assert_area(&framed, 800.);
+
+        let inner = square(5., 5., 20.);
+        assert_area(&framed.intersection(&inner), 300.);
+
+        let union = framed.union(&inner);
+        assert_area(&union, 900.);
+        assert_eq!(union.separate().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn curves_and_tangency() {
+        let a = Circle::new((0., 0.), 10.);
+        let b = Circle::new((20., 0.), 10.);
+
+        // Touching at a single point, nothing is shared
+        assert!(a.intersection(&b).commands().is_empty());
+        assert!((a.union(&b).area() - 2. * a.area()).abs() < 1.);
+    }
+
+    #[test]
+    fn self_intersection() {
+        // A bowtie's lobes wind in opposite directions, both filled under
+        // the nonzero rule
+        let bowtie = Poly::new(&[point(0, 0), point(10, 10), point(10, 0), point(0, 10)]).unwrap();
+        let unioned = bowtie.union(&square(100., 100., 1.));
+        assert_eq!(unioned.separate().unwrap().len(), 3);
+        assert_area(&unioned, 51.);
+    }
+}
@@ -1,9 +1,10 @@
+use super::Ellipse;
 use super::Vec2;
 use super::{Path, Point, Shape, Shaped, DEFAULT_TOLERANCE};
 use super::{DEFAULT_ACCURACY, DEFAULT_STROKE_WIDTH};
-use kurbo::BezPath;
 use kurbo::Circle as KurboCircle;
 use kurbo::Shape as KurboShape;
+use kurbo::{Affine, BezPath};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Circle {
@@ -36,6 +37,33 @@ impl Circle {
             stroke_width: self.stroke_width,
         }
     }
+
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        self.stroke_width = stroke_width;
+    }
+
+    /// Applies `affine` to the circle. Rotations, reflections, uniform scales
+    /// and translations keep it a `Circle`, while anything that would
+    /// stretch it produces an equivalent `Ellipse` instead.
+    pub fn transform(&self, affine: Affine) -> Shape {
+        let [a, b, c, d, _, _] = affine.as_coeffs();
+        let margin = 1e-9 * (a.abs() + b.abs() + c.abs() + d.abs());
+        let is_similarity = ((a - d).abs() <= margin && (b + c).abs() <= margin)
+            || ((a + d).abs() <= margin && (b - c).abs() <= margin);
+        if is_similarity {
+            Shape::Circle(Self {
+                inner: KurboCircle::new(
+                    affine * self.inner.center,
+                    self.inner.radius * affine.determinant().abs().sqrt(),
+                ),
+                stroke_width: self.stroke_width,
+            })
+        } else {
+            let mut ellipse = Ellipse::from(affine * self.inner);
+            ellipse.set_stroke_width(self.stroke_width);
+            Shape::Ellipse(ellipse)
+        }
+    }
 }
 
 impl Shaped for Circle {
@@ -82,6 +110,25 @@ mod tests {
         assert!(!c.contains((0., 0.).into()));
     }
 
+    #[test]
+    fn circle_transform() {
+        let c = Circle::new((10., 10.), 5.);
+        let rotated = c
+            .rotate_about(1., point(0, 0))
+            .mirror(point(0, 0), point(1, 2));
+        assert!(matches!(rotated, Shape::Circle(r) if (r.radius() - 5.).abs() < 1e-9));
+
+        match c.scale_about(2., 1., point(10, 10)) {
+            Shape::Ellipse(e) => {
+                assert!(point_rel_eq(e.center(), point(10, 10), 1e-9));
+                let radii = e.radii();
+                assert!((radii.x.max(radii.y) - 10.).abs() < 1e-9);
+                assert!((radii.x.min(radii.y) - 5.).abs() < 1e-9);
+            }
+            _ => panic!("Non-uniformly scaled circle should be an ellipse"),
+        }
+    }
+
     fn point_rel_eq(p0: Point, p1: Point, margin: f64) -> bool {
         let sub = p0 - p1;
         sub.x.abs() < margin && sub.y.abs() < margin
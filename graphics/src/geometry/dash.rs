@@ -0,0 +1,217 @@
+use super::measure::MeasuredPath;
+use super::{Path, PathEl, DEFAULT_TOLERANCE};
+use kurbo::{BezPath, Circle as KurboCircle, Shape as KurboShape};
+
+/// A repeating pattern of dashes and gaps for breaking a shape's outline
+/// into separate strokes, since plotters can't draw dashed lines themselves.
+///
+/// All lengths are in canvas units along the outline, and the pattern
+/// restarts at the beginning of each subpath.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DashPattern {
+    lengths: Vec<f64>,
+    offset: f64,
+    adaptive: bool,
+}
+
+impl DashPattern {
+    /// Creates a pattern of alternating dash and gap lengths, starting with a
+    /// dash. Like SVG's `stroke-dasharray`, a list with an odd number of
+    /// lengths is repeated to make it even.
+    pub fn new(lengths: &[f64]) -> Self {
+        let mut lengths = lengths.to_vec();
+        if lengths.len() % 2 == 1 {
+            lengths.extend_from_within(..);
+        }
+        Self {
+            lengths,
+            offset: 0.,
+            adaptive: false,
+        }
+    }
+
+    /// Starts the pattern `offset` into its first repetition
+    pub fn offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Stretches or squeezes the pattern along each subpath so that it fits a
+    /// whole number of times, and so that closed subpaths don't have a broken
+    /// dash or gap where they start. Open subpaths end on a complete dash.
+    pub fn adaptive(mut self) -> Self {
+        self.adaptive = true;
+        self
+    }
+
+    fn period(&self) -> f64 {
+        self.lengths.iter().sum()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.lengths.iter().all(|l| *l >= 0.) && self.period() > 0.
+    }
+
+    /// Returns the start and end of each dash along a subpath of `length`
+    fn dashes(&self, length: f64, closed: bool) -> Vec<(f64, f64)> {
+        let period = self.period();
+        let scale = match (self.adaptive, closed) {
+            (false, _) => 1.,
+            (true, true) => length / (length / period).round().max(1.) / period,
+            (true, false) => {
+                // Leaves room for the dash that the subpath ends on
+                let first = self.lengths[0];
+                let repeats = ((length - first) / period).round().max(0.);
+                length / (repeats * period + first)
+            }
+        };
+
+        let mut dashes = vec![];
+        let mut distance = -self.offset.rem_euclid(period) * scale;
+        let mut i = 0;
+        while distance < length {
+            let next = distance + self.lengths[i % self.lengths.len()] * scale;
+            if i % 2 == 0 && next > 0. {
+                dashes.push((distance.max(0.), next.min(length)));
+            }
+            distance = next;
+            i += 1;
+        }
+        dashes
+    }
+}
+
+/// Breaks `path` into the dashes of `pattern`, returning the whole path if
+/// the pattern has no dashes or a negative length
+pub(crate) fn dash(path: &BezPath, pattern: &DashPattern) -> Path {
+    if !pattern.is_valid() {
+        return path.clone().into();
+    }
+    let measured = MeasuredPath::new(path);
+    let mut dashed = BezPath::new();
+    for (start, end, closed) in measured.subpaths() {
+        let length = end - start;
+        let mut dashes = pattern.dashes(length, closed);
+        // On closed subpaths, a dash running through the start is drawn
+        // as one stroke rather than two
+        let mut wrapped = None;
+        if closed && dashes.len() > 1 {
+            let (first, last) = (dashes[0], dashes[dashes.len() - 1]);
+            if first.0 <= 0. && last.1 >= length {
+                dashes.pop();
+                dashes.remove(0);
+                wrapped = Some((last.0, first.1));
+            }
+        }
+        for (a, b) in dashes.into_iter().filter(|(a, b)| b > a) {
+            dashed.extend(measured.between(start + a, start + b));
+        }
+        if let Some((a, b)) = wrapped {
+            dashed.extend(measured.between(start + a, end));
+            let rest = measured.between(start, start + b);
+            dashed.extend(rest.iter().skip(1));
+        }
+    }
+    dashed.into()
+}
+
+/// Places dots about `spacing` apart along each subpath of `path`, adjusted
+/// so that they're spread evenly and open subpaths have a dot at each end.
+/// Dots are circles of `radius`, or single points that the pen just touches
+/// down on if `radius` is zero.
+pub(crate) fn stipple(path: &BezPath, spacing: f64, radius: f64) -> Path {
+    let mut dots = BezPath::new();
+    if spacing <= 0. {
+        return dots.into();
+    }
+    let measured = MeasuredPath::new(path);
+    for (start, end, closed) in measured.subpaths() {
+        let length = end - start;
+        let intervals = (length / spacing).round().max(1.) as usize;
+        let count = if closed { intervals } else { intervals + 1 };
+        for i in 0..count {
+            let point = measured
+                .position(start + length * i as f64 / intervals as f64)
+                .point;
+            if radius > 0. {
+                dots.extend(KurboCircle::new(point, radius).path_elements(DEFAULT_TOLERANCE));
+            } else {
+                dots.extend([PathEl::MoveTo(point), PathEl::LineTo(point)]);
+            }
+        }
+    }
+    dots.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line, Poly, Shaped};
+
+    fn lengths(path: &Path) -> Vec<f64> {
+        path.separate()
+            .unwrap()
+            .iter()
+            .map(|p| (p.perimeter() * 1e6).round() / 1e6)
+            .collect()
+    }
+
+    #[test]
+    fn dashes() {
+        let line = Line::new(point(0, 0), point(10, 0)).unwrap();
+        let dashed = line.dash(&DashPattern::new(&[3., 1.]));
+        assert_eq!(lengths(&dashed), vec![3., 3., 2.]);
+
+        // Odd patterns repeat, so dashes and gaps swap on every other pass
+        let dashed = line.dash(&DashPattern::new(&[2., 1., 1.]));
+        assert_eq!(lengths(&dashed), vec![2., 1., 1., 2.]);
+
+        let dashed = line.dash(&DashPattern::new(&[3., 1.]).offset(2.));
+        assert_eq!(lengths(&dashed), vec![1., 3., 3.]);
+        assert_eq!(dashed.position_at(0.).point, point(0, 0));
+
+        // Open subpaths end on a whole dash when adaptive
+        let dashed = line.dash(&DashPattern::new(&[3., 2.]).adaptive());
+        assert_eq!(lengths(&dashed), vec![3.75, 3.75]);
+
+        let solid = line.dash(&DashPattern::new(&[0., 0.]));
+        assert_eq!(lengths(&solid), vec![10.]);
+    }
+
+    #[test]
+    fn closed_dashes() {
+        let square = Poly::new(&[point(0, 0), point(10, 0), point(10, 10), point(0, 10)]).unwrap();
+        // The dash through the start is joined with the one before it
+        let dashed = square.dash(&DashPattern::new(&[3., 2.]).offset(1.));
+        assert_eq!(lengths(&dashed), vec![3.; 8]);
+        let wrapped = &dashed.separate().unwrap()[7];
+        assert!(wrapped.position_at(2.).point.distance(point(1, 0)) < 1e-9);
+
+        let circle = Circle::new((0., 0.), 10.);
+        let dashed = circle.dash(&DashPattern::new(&[5., 5.]).adaptive());
+        let dashes = lengths(&dashed);
+        assert_eq!(dashes.len(), 6);
+        for dash in dashes {
+            assert!((dash - circle.perimeter() / 12.).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn stipples() {
+        let line = Line::new(point(0, 0), point(10, 0)).unwrap();
+        let dots = line.stipple(3., 0.);
+        let points: Vec<_> = dots
+            .separate()
+            .unwrap()
+            .iter()
+            .map(|d| d.position_at(0.).point)
+            .collect();
+        assert_eq!(points.len(), 4);
+        assert!((points[1].x - 10. / 3.).abs() < 1e-9 && points[3] == point(10, 0));
+
+        let circle = Circle::new((0., 0.), 10.);
+        let dots = circle.stipple(circle.perimeter() / 10., 0.5);
+        assert_eq!(dots.separate().unwrap().len(), 10);
+        assert!((dots.area().abs() - 10. * 0.25 * std::f64::consts::PI).abs() < 1e-2);
+    }
+}
@@ -1,9 +1,9 @@
 use super::Vec2;
 use super::{Path, Point, Shape, Shaped, DEFAULT_TOLERANCE};
 use super::{DEFAULT_ACCURACY, DEFAULT_STROKE_WIDTH};
-use kurbo::BezPath;
 use kurbo::Ellipse as KurboEllipse;
 use kurbo::Shape as KurboShape;
+use kurbo::{Affine, BezPath};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ellipse {
@@ -39,6 +39,26 @@ impl Ellipse {
             stroke_width: self.stroke_width,
         }
     }
+
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        self.stroke_width = stroke_width;
+    }
+
+    pub fn transform(&self, affine: Affine) -> Self {
+        Self {
+            inner: affine * self.inner,
+            stroke_width: self.stroke_width,
+        }
+    }
+}
+
+impl From<KurboEllipse> for Ellipse {
+    fn from(inner: KurboEllipse) -> Self {
+        Self {
+            inner,
+            stroke_width: DEFAULT_STROKE_WIDTH,
+        }
+    }
 }
 
 impl Shaped for Ellipse {
@@ -9,6 +9,7 @@ pub enum GeomError {
     MalformedPath(String),
     MalformedPoly(String),
     FontError(String),
+    SvgError(String),
     IoError(std::io::Error),
 }
 impl From<std::io::Error> for GeomError {
@@ -31,6 +32,9 @@ impl GeomError {
     pub fn font_error(msg: &str) -> Self {
         Self::FontError(msg.to_string())
     }
+    pub fn svg_error(msg: &str) -> Self {
+        Self::SvgError(msg.to_string())
+    }
 }
 
 impl fmt::Display for GeomError {
@@ -40,6 +44,7 @@ impl fmt::Display for GeomError {
             Self::MalformedPoly(msg) => write!(f, "MalformedPoly: {}", msg),
             Self::MalformedPath(msg) => write!(f, "MalformedPath: {}", msg),
             Self::FontError(msg) => write!(f, "FontError: {}", msg),
+            Self::SvgError(msg) => write!(f, "SvgError: {}", msg),
             Self::IoError(e) => write!(f, "IoError: {}", e),
         }
     }
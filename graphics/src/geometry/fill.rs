@@ -0,0 +1,315 @@
+use super::boolean::rings_to_path;
+use super::offset::{offset_rings, Join};
+use super::region::Region;
+use super::{Path, Point, Vec2, DEFAULT_TOLERANCE};
+use kurbo::{Affine, BezPath};
+use std::f64::consts::TAU;
+
+/// Plotter-friendly line patterns that can fill the inside of a shape.
+///
+/// All distances are in canvas units, and angles are in radians measured
+/// from the positive x axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Fill {
+    /// Parallel straight lines `spacing` apart, drawn as separate strokes
+    Hatch { angle: f64, spacing: f64 },
+    /// Two perpendicular hatches overlaid on each other
+    CrossHatch { angle: f64, spacing: f64 },
+    /// Repeated insets of the shape's outline, `spacing` apart
+    Concentric { spacing: f64 },
+    /// Parallel lines joined end to end along the shape's outline, so that
+    /// each area is filled with as few pen lifts as possible
+    ZigZag { angle: f64, spacing: f64 },
+    /// An Archimedean spiral with arms `spacing` apart, centered on the
+    /// shape's bounding box
+    Spiral { spacing: f64 },
+}
+
+impl Fill {
+    /// Generates the pattern's strokes for the area that `path` encloses
+    /// under the nonzero rule. Open subpaths are treated as closed.
+    pub(crate) fn strokes(&self, path: &BezPath) -> Path {
+        match *self {
+            Self::Hatch { angle, spacing } => hatch(path, angle, spacing, false),
+            Self::CrossHatch { angle, spacing } => {
+                let mut hatched = hatch(path, angle, spacing, false);
+                hatched.append(&hatch(path, angle + TAU / 4., spacing, false));
+                hatched
+            }
+            Self::Concentric { spacing } => concentric(path, spacing),
+            Self::ZigZag { angle, spacing } => hatch(path, angle, spacing, true),
+            Self::Spiral { spacing } => spiral(path, spacing),
+        }
+    }
+}
+
+fn polylines_to_bezpath(polylines: &[Vec<Point>]) -> BezPath {
+    let mut bez = BezPath::new();
+    for line in polylines.iter().filter(|l| l.len() > 1) {
+        bez.move_to(line[0]);
+        for p in line.iter().skip(1) {
+            bez.line_to(*p);
+        }
+    }
+    bez
+}
+
+fn hatch(path: &BezPath, angle: f64, spacing: f64, zig_zag: bool) -> Path {
+    if spacing <= 0. {
+        return BezPath::new().into();
+    }
+
+    // Rotate the shape so that hatch lines run along the x axis
+    let mut rotated = path.clone();
+    rotated.apply_affine(Affine::rotate(-angle));
+    let region = Region::from_bezpath(&rotated);
+    if region.is_empty() {
+        return BezPath::new().into();
+    }
+
+    let (min_y, max_y) = region
+        .rings()
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(p.y), hi.max(p.y))
+        });
+
+    // Alternate the direction of each line to minimize travel between them
+    let mut rows: Vec<Vec<(Point, Point)>> = vec![];
+    let mut y = min_y + spacing / 2.;
+    while y < max_y {
+        let reverse = rows.len() % 2 == 1;
+        let mut row: Vec<(Point, Point)> = region
+            .spans(y)
+            .into_iter()
+            .map(|(x0, x1)| {
+                if reverse {
+                    (Point::new(x1, y), Point::new(x0, y))
+                } else {
+                    (Point::new(x0, y), Point::new(x1, y))
+                }
+            })
+            .collect();
+        if reverse {
+            row.reverse();
+        }
+        rows.push(row);
+        y += spacing;
+    }
+
+    let polylines = if zig_zag {
+        join_rows(&region, &rows)
+    } else {
+        rows.into_iter()
+            .flatten()
+            .map(|(a, b)| vec![a, b])
+            .collect()
+    };
+
+    let mut strokes = polylines_to_bezpath(&polylines);
+    strokes.apply_affine(Affine::rotate(angle));
+    strokes.into()
+}
+
+/// Chains hatch spans on consecutive rows into single strokes wherever the
+/// connection between them stays within the region.
+fn join_rows(region: &Region, rows: &[Vec<(Point, Point)>]) -> Vec<Vec<Point>> {
+    let mut used: Vec<Vec<bool>> = rows.iter().map(|r| vec![false; r.len()]).collect();
+    let mut polylines = vec![];
+
+    for row in 0..rows.len() {
+        for span in 0..rows[row].len() {
+            if used[row][span] {
+                continue;
+            }
+            used[row][span] = true;
+            let (a, b) = rows[row][span];
+            let mut polyline = vec![a, b];
+            let mut current_row = row;
+
+            while current_row + 1 < rows.len() {
+                let end = *polyline.last().unwrap();
+                let next_row = current_row + 1;
+                let next = rows[next_row]
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !used[next_row][*i])
+                    .flat_map(|(i, &(a, b))| [(i, a, b), (i, b, a)])
+                    .filter(|(_, start, _)| {
+                        region.contains(end.midpoint(*start)) && !region.crosses(end, *start)
+                    })
+                    .min_by(|x, y| end.distance(x.1).partial_cmp(&end.distance(y.1)).unwrap());
+                match next {
+                    Some((i, start, finish)) => {
+                        used[next_row][i] = true;
+                        polyline.push(start);
+                        polyline.push(finish);
+                        current_row = next_row;
+                    }
+                    None => break,
+                }
+            }
+            polylines.push(polyline);
+        }
+    }
+    polylines
+}
+
+fn concentric(path: &BezPath, spacing: f64) -> Path {
+    if spacing <= 0. {
+        return BezPath::new().into();
+    }
+
+    let mut levels = vec![];
+    let mut current = Region::from_bezpath(path).rings().to_vec();
+    loop {
+        current = offset_rings(&current, -spacing, Join::Round);
+        if current.is_empty() {
+            break;
+        }
+        levels.extend(current.iter().cloned());
+    }
+    rings_to_path(&levels)
+}
+
+fn spiral(path: &BezPath, spacing: f64) -> Path {
+    let region = Region::from_bezpath(path);
+    if spacing <= 0. || region.is_empty() {
+        return BezPath::new().into();
+    }
+
+    let points = region.rings().iter().flatten();
+    let (min, max) = points.fold(
+        (
+            Point::new(f64::INFINITY, f64::INFINITY),
+            Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        ),
+        |(min, max), p| {
+            (
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+    let center = min.midpoint(max);
+    let max_radius = (max - center).hypot() + spacing;
+
+    // Step along the spiral so that each chord deviates from the true curve
+    // by no more than the default tolerance
+    let mut points = vec![center];
+    let mut th: f64 = 0.;
+    loop {
+        let radius = spacing * th / TAU;
+        if radius > max_radius {
+            break;
+        }
+        let step = if radius > DEFAULT_TOLERANCE {
+            (2. * (1. - DEFAULT_TOLERANCE / radius).acos()).min(0.25)
+        } else {
+            0.25
+        };
+        th += step;
+        let radius = spacing * th / TAU;
+        points.push(center + Vec2::from_angle(th) * radius);
+    }
+
+    polylines_to_bezpath(&region.clip_polyline(&points, true)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Poly, Shaped};
+    use kurbo::ParamCurve;
+
+    fn framed_square() -> Path {
+        let mut framed = Poly::new(&[point(0, 0), point(30, 0), point(30, 30), point(0, 30)])
+            .unwrap()
+            .to_path();
+        framed.move_to(point(10, 10));
+        framed.line_to(point(10, 20));
+        framed.line_to(point(20, 20));
+        framed.line_to(point(20, 10));
+        framed.close();
+        framed
+    }
+
+    fn in_hole(p: Point) -> bool {
+        p.x > 10. + 1e-6 && p.x < 20. - 1e-6 && p.y > 10. + 1e-6 && p.y < 20. - 1e-6
+    }
+
+    #[test]
+    fn hatch_respects_holes() {
+        let framed = framed_square();
+        let hatch = framed.fill(Fill::Hatch {
+            angle: 0.,
+            spacing: 2.,
+        });
+
+        // 15 rows, 5 of which are split in two by the hole
+        assert_eq!(hatch.separate().unwrap().len(), 20);
+        assert!((hatch.perimeter() - (10. * 30. + 5. * 20.)).abs() < 1e-6);
+        for seg in hatch.inner().segments() {
+            assert!(!in_hole(seg.eval(0.5)));
+        }
+    }
+
+    #[test]
+    fn cross_hatch_doubles_hatch() {
+        let square = Poly::new(&[point(0, 0), point(10, 0), point(10, 10), point(0, 10)]).unwrap();
+        let cross_hatch = square.fill(Fill::CrossHatch {
+            angle: 0.,
+            spacing: 1.,
+        });
+        assert!((cross_hatch.perimeter() - 200.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn zig_zag_is_single_stroke() {
+        let circle = Circle::new((0., 0.), 20.);
+        let zig_zag = circle.fill(Fill::ZigZag {
+            angle: 0.3,
+            spacing: 2.,
+        });
+        assert_eq!(zig_zag.separate().unwrap().len(), 1);
+
+        // Around a hole, the stroke must split to go around either side
+        let framed = framed_square().fill(Fill::ZigZag {
+            angle: 0.,
+            spacing: 2.,
+        });
+        assert!(framed.separate().unwrap().len() > 1);
+        for seg in framed.inner().segments() {
+            assert!(!in_hole(seg.eval(0.5)));
+        }
+    }
+
+    #[test]
+    fn concentric_levels() {
+        let square = Poly::new(&[point(0, 0), point(10, 0), point(10, 10), point(0, 10)]).unwrap();
+        let concentric = square.fill(Fill::Concentric { spacing: 2. });
+        let levels = concentric.separate().unwrap();
+
+        // Insets down to 6 and then 2 unit wide squares
+        assert_eq!(levels.len(), 2);
+        assert!((concentric.perimeter() - 4. * (6. + 2.)).abs() < 1e-6);
+
+        let framed = framed_square().fill(Fill::Concentric { spacing: 2. });
+        for seg in framed.inner().segments() {
+            assert!(!in_hole(seg.eval(0.5)));
+        }
+    }
+
+    #[test]
+    fn spiral_stays_inside() {
+        let framed = framed_square();
+        let spiral = framed.fill(Fill::Spiral { spacing: 2. });
+        assert!(spiral.separate().unwrap().len() > 1);
+        for seg in spiral.inner().segments() {
+            let mid = seg.eval(0.5);
+            assert!(!in_hole(mid));
+            assert!(framed.contains(mid));
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use super::{Affine, GeomError, GeomResult, PathEl, Point, StrokeFont};
+use rusttype::{OutlineBuilder, Scale};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Name of the outline font text is drawn with by default
+pub const DEFAULT_FONT: &str = "Jost Medium";
+
+/// Name of the single-line font text is drawn with by default
+pub const DEFAULT_SINGLE_LINE_FONT: &str = "Nightgraph Single Line";
+
+/// Fonts that have been loaded, by name
+static FONTS: Mutex<Option<HashMap<String, Font>>> = Mutex::new(None);
+
+/// A parsed font, which is cheap to clone and share between text builds.
+///
+/// TrueType and OpenType fonts are drawn as outlines, and SVG fonts as
+/// single-line strokes.
+#[derive(Clone)]
+pub struct Font {
+    kind: Arc<FontKind>,
+}
+
+enum FontKind {
+    Outline(rusttype::Font<'static>),
+    Stroke(StrokeFont),
+}
+
+impl Font {
+    /// Parses a TrueType, OpenType or SVG font, using `name` to describe it
+    /// in errors
+    pub fn from_bytes(name: &str, bytes: Vec<u8>) -> GeomResult<Self> {
+        let kind = if bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'<') {
+            let content = String::from_utf8(bytes).map_err(|_| {
+                GeomError::font_error(&format!("SVG font '{}' isn't valid UTF-8", name))
+            })?;
+            let font = StrokeFont::from_svg(&content).map_err(|e| match e {
+                GeomError::FontError(msg) => GeomError::font_error(&format!("{}: {}", name, msg)),
+                e => e,
+            })?;
+            FontKind::Stroke(font)
+        } else {
+            let font = rusttype::Font::try_from_vec(bytes).ok_or_else(|| {
+                GeomError::font_error(&format!(
+                    "'{}' isn't a valid TrueType or OpenType font",
+                    name
+                ))
+            })?;
+            FontKind::Outline(font)
+        };
+        Ok(Self {
+            kind: Arc::new(kind),
+        })
+    }
+
+    /// Returns whether the font's glyphs are single-line strokes rather than
+    /// outlines
+    pub fn is_single_line(&self) -> bool {
+        matches!(*self.kind, FontKind::Stroke(_))
+    }
+
+    /// Returns the ascent and descent of the font at a size
+    pub(crate) fn v_metrics(&self, size: f64) -> (f64, f64) {
+        match &*self.kind {
+            FontKind::Outline(font) => {
+                let v_metrics = font.v_metrics(Scale::uniform(size as f32));
+                (v_metrics.ascent as f64, v_metrics.descent as f64)
+            }
+            FontKind::Stroke(font) => {
+                let scale = size / font.height();
+                (font.ascent() * scale, font.descent() * scale)
+            }
+        }
+    }
+
+    pub(crate) fn advance(&self, c: char, size: f64) -> f64 {
+        match &*self.kind {
+            FontKind::Outline(font) => {
+                font.glyph(c)
+                    .scaled(Scale::uniform(size as f32))
+                    .h_metrics()
+                    .advance_width as f64
+            }
+            FontKind::Stroke(font) => font.glyph(c).advance * size / font.height(),
+        }
+    }
+
+    pub(crate) fn kerning(&self, first: char, second: char, size: f64) -> f64 {
+        match &*self.kind {
+            FontKind::Outline(font) => {
+                font.pair_kerning(Scale::uniform(size as f32), first, second) as f64
+            }
+            FontKind::Stroke(font) => font.kerning(first, second) * size / font.height(),
+        }
+    }
+
+    /// Returns the commands drawing a glyph with its origin on the baseline
+    /// at (0, 0), and the y axis pointing down
+    pub(crate) fn outline(&self, c: char, size: f64) -> Vec<PathEl> {
+        match &*self.kind {
+            FontKind::Outline(font) => {
+                let mut path_outliner = PathOutlineBuilder::default();
+                font.glyph(c)
+                    .scaled(Scale::uniform(size as f32))
+                    .build_outline(&mut path_outliner);
+                // Whitespace has no outline, and produces no commands
+                path_outliner.cmds
+            }
+            FontKind::Stroke(font) => {
+                let scale = size / font.height();
+                // Glyphs are drawn with the y axis pointing up from the baseline
+                let flip = Affine::new([scale, 0., 0., -scale, 0., 0.]);
+                (flip * font.glyph(c).path.clone()).elements().to_vec()
+            }
+        }
+    }
+}
+
+/// Parses and registers a font under `name`, replacing any font already
+/// registered with that name. This doesn't touch the filesystem, so works
+/// wherever fonts can't be read from files, such as in WASM.
+pub fn register_font(name: &str, bytes: Vec<u8>) -> GeomResult<Font> {
+    let font = Font::from_bytes(name, bytes)?;
+    let mut fonts = FONTS.lock().unwrap();
+    fonts
+        .get_or_insert_with(HashMap::new)
+        .insert(name.to_string(), font.clone());
+    Ok(font)
+}
+
+/// Returns the font registered under `name`, which is either one of the
+/// bundled fonts, one added with `register_font`, or the path of a font file,
+/// which is read and registered the first time it's loaded
+pub fn load_font(name: &str) -> GeomResult<Font> {
+    if let Some(font) = FONTS.lock().unwrap().as_ref().and_then(|f| f.get(name)) {
+        return Ok(font.clone());
+    }
+    let bytes = match name {
+        DEFAULT_FONT => include_bytes!("../../../assets/fonts/Jost-500-Medium.otf").to_vec(),
+        DEFAULT_SINGLE_LINE_FONT => {
+            include_bytes!("../../../assets/fonts/NightgraphSingleLine.svg").to_vec()
+        }
+        path => std::fs::read(path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("unable to read font '{}': {}", path, e))
+        })?,
+    };
+    register_font(name, bytes)
+}
+
+#[derive(Default)]
+struct PathOutlineBuilder {
+    cmds: Vec<PathEl>,
+}
+
+impl OutlineBuilder for PathOutlineBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.cmds.push(PathEl::MoveTo(point(x, y)));
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.cmds.push(PathEl::LineTo(point(x, y)));
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.cmds.push(PathEl::QuadTo(point(x1, y1), point(x, y)));
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.cmds
+            .push(PathEl::CurveTo(point(x1, y1), point(x2, y2), point(x, y)));
+    }
+    fn close(&mut self) {
+        self.cmds.push(PathEl::ClosePath);
+    }
+}
+
+fn point(x: f32, y: f32) -> Point {
+    Point::new(x as f64, y as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry() {
+        let svg = br#"<svg><font><glyph unicode="a" horiz-adv-x="1"/></font></svg>"#;
+        let registered = register_font("test registry", svg.to_vec()).unwrap();
+        assert!(registered.is_single_line());
+        // The same parsed font is handed out rather than parsing it again
+        let loaded = load_font("test registry").unwrap();
+        assert!(Arc::ptr_eq(&registered.kind, &loaded.kind));
+
+        assert!(!load_font(DEFAULT_FONT).unwrap().is_single_line());
+        assert!(load_font(DEFAULT_SINGLE_LINE_FONT)
+            .unwrap()
+            .is_single_line());
+    }
+
+    #[test]
+    fn errors() {
+        let message = |result: GeomResult<Font>| match result {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("expected an error"),
+        };
+        assert_eq!(
+            message(register_font("junk", vec![0, 1, 2])),
+            "FontError: 'junk' isn't a valid TrueType or OpenType font"
+        );
+        assert_eq!(
+            message(register_font("empty", b"<svg></svg>".to_vec())),
+            "FontError: empty: SVG font has no glyphs"
+        );
+        assert!(message(load_font("/nonexistent/font.otf")).contains("/nonexistent/font.otf"));
+    }
+}
@@ -2,7 +2,7 @@ use super::{
     GeomResult, Path, Point, Shape, Shaped, Vec2, DEFAULT_ACCURACY, DEFAULT_STROKE_WIDTH,
     DEFAULT_TOLERANCE,
 };
-use kurbo::{BezPath, Line as KurboLine, ParamCurve, Shape as KurboShape};
+use kurbo::{Affine, BezPath, Line as KurboLine, ParamCurve, Shape as KurboShape};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Line {
@@ -39,6 +39,17 @@ impl Line {
             stroke_width: self.stroke_width,
         }
     }
+
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        self.stroke_width = stroke_width;
+    }
+
+    pub fn transform(&self, affine: Affine) -> Self {
+        Self {
+            inner: affine * self.inner,
+            stroke_width: self.stroke_width,
+        }
+    }
 }
 
 impl Shaped for Line {
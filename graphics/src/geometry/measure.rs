@@ -0,0 +1,285 @@
+use super::{Point, Vec2, DEFAULT_ACCURACY};
+use kurbo::{
+    BezPath, CubicBez, Line, ParamCurve, ParamCurveArclen, ParamCurveDeriv, PathEl, PathSeg,
+    QuadBez,
+};
+
+/// A point along a shape's outline, with the direction the outline runs in
+/// there
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathPosition {
+    pub point: Point,
+    /// Unit vector in the direction of travel
+    pub tangent: Vec2,
+    /// Unit vector a quarter turn clockwise from the tangent, which is to the
+    /// right of the direction of travel
+    pub normal: Vec2,
+}
+
+struct MeasuredSeg {
+    seg: PathSeg,
+    length: f64,
+    /// Whether the segment begins a new subpath, rather than continuing on
+    /// from the previous segment
+    starts_subpath: bool,
+}
+
+/// The segments of a path with their arc lengths, for finding positions by
+/// distance along it. Distances skip over the gaps between subpaths.
+pub(crate) struct MeasuredPath {
+    segments: Vec<MeasuredSeg>,
+    length: f64,
+}
+
+impl MeasuredPath {
+    pub(crate) fn new(path: &BezPath) -> Self {
+        let mut segments = vec![];
+        let mut starts_subpath = true;
+        let (mut start, mut last) = (Point::ZERO, Point::ZERO);
+        for el in path.elements() {
+            let seg = match *el {
+                PathEl::MoveTo(p) => {
+                    // Subpaths are kept apart even when one starts where the
+                    // previous one ended
+                    starts_subpath = true;
+                    start = p;
+                    last = p;
+                    continue;
+                }
+                PathEl::LineTo(p) => PathSeg::Line(Line::new(last, p)),
+                PathEl::QuadTo(p1, p2) => PathSeg::Quad(QuadBez::new(last, p1, p2)),
+                PathEl::CurveTo(p1, p2, p3) => PathSeg::Cubic(CubicBez::new(last, p1, p2, p3)),
+                PathEl::ClosePath if last != start => PathSeg::Line(Line::new(last, start)),
+                PathEl::ClosePath => continue,
+            };
+            last = seg.end();
+            segments.push(MeasuredSeg {
+                seg,
+                length: seg.arclen(DEFAULT_ACCURACY),
+                starts_subpath,
+            });
+            starts_subpath = false;
+        }
+        let length = segments.iter().map(|s| s.length).sum();
+        Self { segments, length }
+    }
+
+    pub(crate) fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// Returns the distances along the path at which each subpath starts and
+    /// ends, and whether it ends where it started
+    pub(crate) fn subpaths(&self) -> Vec<(f64, f64, bool)> {
+        let mut subpaths: Vec<(f64, f64, bool)> = vec![];
+        let mut start_point = Point::ZERO;
+        let mut offset = 0.;
+        for s in &self.segments {
+            if s.starts_subpath {
+                start_point = s.seg.start();
+                subpaths.push((offset, offset, false));
+            }
+            offset += s.length;
+            if let Some(last) = subpaths.last_mut() {
+                *last = (last.0, offset, s.seg.end().distance(start_point) < 1e-9);
+            }
+        }
+        subpaths
+    }
+
+    /// Returns the index of the segment at `distance` along the path, and the
+    /// curve parameter of the point on it, clamping distances to the path
+    fn locate(&self, distance: f64) -> Option<(usize, f64)> {
+        let mut remaining = distance.max(0.);
+        for (n, s) in self.segments.iter().enumerate() {
+            if remaining <= s.length || n == self.segments.len() - 1 {
+                let t = match remaining {
+                    r if r >= s.length => 1.,
+                    r if r <= 0. => 0.,
+                    r => s.seg.inv_arclen(r, DEFAULT_ACCURACY),
+                };
+                return Some((n, t));
+            }
+            remaining -= s.length;
+        }
+        None
+    }
+
+    /// Returns the position at `distance` along the path, which is clamped to
+    /// the path's length
+    pub(crate) fn position(&self, distance: f64) -> PathPosition {
+        let (n, seg, t) = match self.locate(distance) {
+            Some((n, t)) => (n, self.segments[n].seg, t),
+            None => {
+                return PathPosition {
+                    point: Point::ZERO,
+                    tangent: Vec2::new(1., 0.),
+                    normal: Vec2::new(0., 1.),
+                }
+            }
+        };
+        let mut tangent = match seg {
+            PathSeg::Line(l) => l.deriv().eval(t),
+            PathSeg::Quad(q) => q.deriv().eval(t),
+            PathSeg::Cubic(c) => c.deriv().eval(t),
+        }
+        .to_vec2();
+        // Curves whose control points coincide with an end have no
+        // derivative there
+        if tangent.hypot() < 1e-12 {
+            tangent = self.direction(n);
+        }
+        let tangent = tangent.normalize();
+        PathPosition {
+            point: seg.eval(t),
+            tangent,
+            normal: Vec2::new(-tangent.y, tangent.x),
+        }
+    }
+
+    /// Returns the direction from the start to the end of the `n`th segment.
+    /// Segments of zero length take the direction of the nearest segment
+    /// that has one, or of the x axis if none do.
+    fn direction(&self, n: usize) -> Vec2 {
+        let len = self.segments.len();
+        (0..len)
+            .flat_map(|d| [n.checked_sub(d), Some(n + d)])
+            .flatten()
+            .filter(|&k| k < len)
+            .map(|k| self.segments[k].seg.end() - self.segments[k].seg.start())
+            .find(|chord| chord.hypot() >= 1e-12)
+            .unwrap_or_else(|| Vec2::new(1., 0.))
+    }
+
+    /// Returns the part of the path between two distances along it, which
+    /// is empty unless `start` comes before `end`
+    pub(crate) fn between(&self, start: f64, end: f64) -> BezPath {
+        let mut path = BezPath::new();
+        let (start, end) = (start.max(0.), end.min(self.length));
+        if start >= end {
+            return path;
+        }
+        let mut offset = 0.;
+        let mut connected = false;
+        for s in &self.segments {
+            let (seg_start, seg_end) = (offset, offset + s.length);
+            offset = seg_end;
+            if seg_end <= start || seg_start >= end {
+                connected = false;
+                continue;
+            }
+            let t = |distance: f64| match distance - seg_start {
+                d if d <= 0. => 0.,
+                d if d >= s.length => 1.,
+                d => s.seg.inv_arclen(d, DEFAULT_ACCURACY),
+            };
+            let piece = s.seg.subsegment(t(start)..t(end));
+            if !connected || s.starts_subpath {
+                path.move_to(piece.start());
+            }
+            match piece {
+                PathSeg::Line(l) => path.line_to(l.p1),
+                PathSeg::Quad(q) => path.quad_to(q.p1, q.p2),
+                PathSeg::Cubic(c) => path.curve_to(c.p1, c.p2, c.p3),
+            }
+            connected = true;
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line, Path, Poly, Shaped};
+    use std::f64::consts::TAU;
+
+    fn close(a: Point, b: Point) -> bool {
+        a.distance(b) < 1e-6
+    }
+
+    #[test]
+    fn positions() {
+        let square = Poly::new(&[point(0, 0), point(10, 0), point(10, 10), point(0, 10)]).unwrap();
+        let position = square.position_at(15.);
+        assert!(close(position.point, point(10, 5)));
+        assert_eq!(position.tangent, Vec2::new(0., 1.));
+        assert_eq!(position.normal, Vec2::new(-1., 0.));
+        // The closing edge is included, and lengths are clamped
+        assert!(close(square.position_at(35.).point, point(0, 5)));
+        assert!(close(square.position_at(50.).point, point(0, 0)));
+        assert!(close(square.position_at_fraction(0.5).point, point(10, 10)));
+
+        let circle = Circle::new((0., 0.), 10.);
+        for fraction in [0., 0.2, 0.45, 0.9] {
+            let position = circle.position_at_fraction(fraction);
+            assert!((position.point.distance(Point::ZERO) - 10.).abs() < 1e-2);
+            let radial = position.point.to_vec2().normalize();
+            assert!(position.tangent.dot(radial).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn resampling() {
+        let line = Line::new(point(0, 0), point(10, 0)).unwrap();
+        let points = line.resample(6);
+        assert_eq!(points.len(), 6);
+        assert!(close(points[1], point(2, 0)) && close(points[5], point(10, 0)));
+
+        // Closed shapes don't repeat their start
+        let circle = Circle::new((0., 0.), 10.);
+        let points = circle.resample(8);
+        assert_eq!(points.len(), 8);
+        let spacing = points[0].distance(points[1]);
+        for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+            assert!((a.distance(*b) - spacing).abs() < 1e-2);
+        }
+        assert!((spacing - 20. * (TAU / 16.).sin()).abs() < 1e-2);
+    }
+
+    #[test]
+    fn splitting() {
+        let mut path = Path::from_points(&[point(0, 0), point(10, 0), point(10, 10)]);
+        path.move_to(point(20, 0));
+        path.line_to(point(30, 0));
+
+        let (before, after) = path.split_at(5.);
+        assert!((before.perimeter() - 5.).abs() < 1e-6);
+        assert!((after.perimeter() - 25.).abs() < 1e-6);
+        assert!(close(after.position_at(0.).point, point(5, 0)));
+
+        // The gap between subpaths is skipped, but kept apart
+        let between = path.subpath_between(15., 25.);
+        assert!((between.perimeter() - 10.).abs() < 1e-6);
+        assert_eq!(between.separate().unwrap().len(), 2);
+        assert!(close(between.position_at(10.).point, point(25, 0)));
+        assert!(path.subpath_between(8., 8.).inner().is_empty());
+    }
+
+    #[test]
+    fn touching_subpaths() {
+        // A subpath that starts where the previous one ended is still separate
+        let mut path = Path::from_points(&[point(0, 0), point(10, 0)]);
+        path.move_to(point(10, 0));
+        path.line_to(point(10, 10));
+        let measured = MeasuredPath::new(path.inner());
+        assert_eq!(measured.subpaths(), [(0., 10., false), (10., 20., false)]);
+    }
+
+    #[test]
+    fn zero_length_directions() {
+        // Dots take the direction of a neighbouring segment, or failing that
+        // of the x axis
+        let mut path = Path::from_points(&[point(5, 5), point(5, 5)]);
+        path.move_to(point(0, 0));
+        path.line_to(point(0, 10));
+        let position = path.position_at(0.);
+        assert_eq!(position.point, point(5, 5));
+        assert_eq!(position.tangent, Vec2::new(0., 1.));
+
+        let dot = Path::from_points(&[point(5, 5), point(5, 5)]);
+        let position = dot.position_at(0.);
+        assert_eq!(position.tangent, Vec2::new(1., 0.));
+        assert_eq!(position.normal, Vec2::new(0., 1.));
+    }
+}
@@ -3,23 +3,43 @@ use kurbo::{
     flatten, BezPath, Line as KurboLine, ParamCurve, ParamCurveNearest, PathSeg,
     Shape as KurboShape,
 };
-pub use kurbo::{PathEl, Point, Vec2, DEFAULT_ACCURACY};
+pub use kurbo::{Affine, PathEl, Point, Vec2, DEFAULT_ACCURACY};
+use measure::MeasuredPath;
 
+mod boolean;
 mod circle;
+mod dash;
 mod ellipse;
 mod error;
+mod fill;
+mod font;
 mod line;
+mod measure;
+mod offset;
 mod path;
 mod poly;
+mod region;
+mod simplify;
+mod stroke_font;
 mod text;
 
+pub use boolean::BooleanOp;
 pub use circle::Circle;
+pub use dash::DashPattern;
 pub use ellipse::Ellipse;
 pub use error::*;
+pub use fill::Fill;
+pub use font::{load_font, register_font, Font, DEFAULT_FONT, DEFAULT_SINGLE_LINE_FONT};
 pub use line::Line;
-pub use path::{Path, PathBuilder};
+pub use measure::PathPosition;
+pub use offset::Join;
+pub use path::{smoothing_control_values, Path, PathBuilder};
 pub use poly::{Poly, PolyBuilder};
-pub use text::TextBuilder;
+pub use simplify::{Simplify, Smooth};
+pub use stroke_font::StrokeFont;
+pub use text::{Glyph, TextAlign, TextAnchor, TextBuilder, TextLine};
+
+pub(crate) use region::{split_subpaths, GridIndex, Region};
 
 /// Convenience function to allow making `Point`s quickly
 /// from any compatible number type
@@ -34,6 +54,7 @@ pub fn avg_point(p1: Point, p2: Point) -> Point {
 pub const DEFAULT_TOLERANCE: f64 = 1e-2;
 pub const DEFAULT_STROKE_WIDTH: f64 = 0.45 * crate::units::MM;
 
+#[derive(Clone)]
 pub enum Shape {
     Path(Path),
     Circle(Circle),
@@ -53,7 +74,7 @@ impl Shape {
         Shape::from(s)
     }
 
-    fn inner(&self) -> &dyn Shaped {
+    pub(crate) fn inner(&self) -> &dyn Shaped {
         match self {
             Self::Path(p) => p,
             Self::Circle(c) => c,
@@ -86,6 +107,73 @@ impl Shape {
             Self::Poly(p) => Self::Poly(p.translate(translation)),
         }
     }
+
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        match self {
+            Self::Circle(c) => c.set_stroke_width(stroke_width),
+            Self::Ellipse(e) => e.set_stroke_width(stroke_width),
+            Self::Path(p) => p.stroke_width = stroke_width,
+            Self::Line(l) => l.set_stroke_width(stroke_width),
+            Self::Poly(p) => p.set_stroke_width(stroke_width),
+        }
+    }
+
+    /// Applies an arbitrary affine transformation to the shape, leaving its
+    /// stroke width unchanged. Circles that are stretched or skewed become
+    /// ellipses.
+    pub fn transform(&self, affine: Affine) -> Self {
+        match self {
+            Self::Circle(c) => c.transform(affine),
+            Self::Ellipse(e) => Self::Ellipse(e.transform(affine)),
+            Self::Path(p) => Self::Path(p.transform(affine)),
+            Self::Line(l) => Self::Line(l.transform(affine)),
+            Self::Poly(p) => Self::Poly(p.transform(affine)),
+        }
+    }
+
+    /// Like `transform`, but also scales the stroke width by the average
+    /// factor by which `affine` scales lengths.
+    pub fn transform_with_stroke(&self, affine: Affine) -> Self {
+        let mut shape = self.transform(affine);
+        shape.set_stroke_width(self.stroke() * affine.determinant().abs().sqrt());
+        shape
+    }
+
+    /// Rotates the shape by `angle` radians around `center`. Positive angles
+    /// rotate clockwise, as the y axis points down.
+    pub fn rotate_about(&self, angle: f64, center: Point) -> Self {
+        self.transform(about(center, Affine::rotate(angle)))
+    }
+
+    /// Scales the shape by `sx` horizontally and `sy` vertically, keeping
+    /// `center` fixed
+    pub fn scale_about(&self, sx: f64, sy: f64, center: Point) -> Self {
+        self.transform(about(center, Affine::scale_non_uniform(sx, sy)))
+    }
+
+    /// Shears the shape, keeping `center` fixed. Each point moves
+    /// horizontally by `kx` times its vertical distance from `center`, and
+    /// vertically by `ky` times its horizontal distance.
+    pub fn skew(&self, kx: f64, ky: f64, center: Point) -> Self {
+        self.transform(about(center, Affine::new([1., ky, kx, 1., 0., 0.])))
+    }
+
+    /// Reflects the shape across the line passing through `a` and `b`
+    pub fn mirror(&self, a: Point, b: Point) -> Self {
+        let dir = b - a;
+        let len2 = dir.hypot2();
+        if len2 == 0. {
+            return self.transform(Affine::default());
+        }
+        let cos = (dir.x * dir.x - dir.y * dir.y) / len2;
+        let sin = 2. * dir.x * dir.y / len2;
+        self.transform(about(a, Affine::new([cos, sin, sin, -cos, 0., 0.])))
+    }
+}
+
+/// Returns `affine` applied as if `center` were the origin
+fn about(center: Point, affine: Affine) -> Affine {
+    Affine::translate(center.to_vec2()) * affine * Affine::translate(-center.to_vec2())
 }
 
 /// Represents the ability to be converted to a path, with optional hatch fill.
@@ -160,6 +248,179 @@ pub trait Shaped {
         BezPath::from_path_segments(result_segs.into_iter()).into()
     }
 
+    /// Returns the boundary of the region formed by combining `self` and
+    /// `other` with `op`. Curves are flattened, and the resulting rings wind
+    /// so that outer boundaries have positive area and holes negative area.
+    fn boolean(&self, other: &dyn Shaped, op: BooleanOp) -> Path {
+        let self_rings = boolean::rings(&self.as_bezpath());
+        let other_rings = boolean::rings(&other.as_bezpath());
+        let mut path =
+            boolean::rings_to_path(&boolean::boolean_rings(&self_rings, &other_rings, op));
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Returns the region covered by either `self` or `other`
+    fn union(&self, other: &dyn Shaped) -> Path {
+        self.boolean(other, BooleanOp::Union)
+    }
+
+    /// Returns the region covered by both `self` and `other`
+    fn intersection(&self, other: &dyn Shaped) -> Path {
+        self.boolean(other, BooleanOp::Intersection)
+    }
+
+    /// Returns the region covered by exactly one of `self` and `other`
+    fn xor(&self, other: &dyn Shaped) -> Path {
+        self.boolean(other, BooleanOp::Xor)
+    }
+
+    /// Returns the outline of the shape grown outward by `distance`, or shrunk
+    /// inward for negative distances, with `join` deciding the shape of its
+    /// corners. Open subpaths are outlined `distance` to either side instead.
+    /// Curves are flattened, and overlaps and anything collapsed by
+    /// shrinking are removed, so the result may split into several
+    /// subpaths, which `Path::separate` returns individually.
+    fn offset(&self, distance: f64, join: Join) -> Path {
+        let mut path = offset::offset(&self.as_bezpath(), distance, join);
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Returns the outline of the area covered by drawing the shape with its
+    /// stroke width, such as a band along the edge of a closed shape. The
+    /// outline itself has the default stroke width.
+    fn stroke_outline(&self, join: Join) -> Path {
+        offset::stroke_outline(&self.as_bezpath(), self.stroke(), join)
+    }
+
+    /// Returns the position at `length` along the shape's outline, measured
+    /// from its start. Lengths are clamped to the outline, and the gaps
+    /// between subpaths aren't counted.
+    fn position_at(&self, length: f64) -> PathPosition {
+        MeasuredPath::new(&self.as_bezpath()).position(length)
+    }
+
+    /// Returns the position a `fraction` of the way along the shape's
+    /// outline, from 0 at its start to 1 at its end
+    fn position_at_fraction(&self, fraction: f64) -> PathPosition {
+        let measured = MeasuredPath::new(&self.as_bezpath());
+        measured.position(fraction * measured.length())
+    }
+
+    /// Returns `count` points spaced evenly along the shape's outline,
+    /// including both of its ends. Closed shapes don't repeat their start,
+    /// so the last point is as far from the first as from its neighbour.
+    fn resample(&self, count: usize) -> Vec<Point> {
+        let bez = self.as_bezpath();
+        let measured = MeasuredPath::new(&bez);
+        let closed = matches!(bez.elements().last(), Some(PathEl::ClosePath));
+        let intervals = match (closed, count) {
+            (true, _) => count,
+            (false, 0) | (false, 1) => 1,
+            (false, _) => count - 1,
+        };
+        (0..count)
+            .map(|i| {
+                let length = measured.length() * i as f64 / intervals as f64;
+                measured.position(length).point
+            })
+            .collect()
+    }
+
+    /// Splits the shape's outline into the parts before and after `length`
+    /// along it
+    fn split_at(&self, length: f64) -> (Path, Path) {
+        let measured = MeasuredPath::new(&self.as_bezpath());
+        let mut before: Path = measured.between(0., length).into();
+        let mut after: Path = measured.between(length, measured.length()).into();
+        before.stroke_width = self.stroke();
+        after.stroke_width = self.stroke();
+        (before, after)
+    }
+
+    /// Returns the part of the shape's outline between two lengths along it,
+    /// which is empty unless `start` comes before `end`
+    fn subpath_between(&self, start: f64, end: f64) -> Path {
+        let mut path: Path = MeasuredPath::new(&self.as_bezpath())
+            .between(start, end)
+            .into();
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Breaks the shape's outline into the separate strokes of a dash
+    /// pattern
+    fn dash(&self, pattern: &DashPattern) -> Path {
+        let mut path = dash::dash(&self.as_bezpath(), pattern);
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Returns dots spread evenly about `spacing` apart along the shape's
+    /// outline, each a circle of `radius`, or a single touch of the pen if
+    /// `radius` is zero
+    fn stipple(&self, spacing: f64, radius: f64) -> Path {
+        let mut path = dash::stipple(&self.as_bezpath(), spacing, radius);
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Returns the shape with the vertices that contribute least to it
+    /// removed. Curves are flattened first, and each subpath is simplified
+    /// separately, with closed subpaths staying closed.
+    fn simplify(&self, method: Simplify) -> Path {
+        let mut path = simplify::simplify(&self.as_bezpath(), method);
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Returns the shape with its corners rounded off. Curves are flattened
+    /// first, and each subpath is smoothed separately, with open subpaths
+    /// keeping their ends and closed subpaths staying closed.
+    fn smooth(&self, method: Smooth) -> Path {
+        let mut path = simplify::smooth(&self.as_bezpath(), method);
+        path.stroke_width = self.stroke();
+        path
+    }
+
+    /// Returns the shape with `affine` applied, leaving its stroke width
+    /// unchanged. See `Shape::transform`.
+    fn transform(&self, affine: Affine) -> Shape {
+        self.as_shape().transform(affine)
+    }
+
+    /// Returns the shape with `affine` applied to both its geometry and its
+    /// stroke width. See `Shape::transform_with_stroke`.
+    fn transform_with_stroke(&self, affine: Affine) -> Shape {
+        self.as_shape().transform_with_stroke(affine)
+    }
+
+    fn rotate_about(&self, angle: f64, center: Point) -> Shape {
+        self.as_shape().rotate_about(angle, center)
+    }
+
+    fn scale_about(&self, sx: f64, sy: f64, center: Point) -> Shape {
+        self.as_shape().scale_about(sx, sy, center)
+    }
+
+    fn skew(&self, kx: f64, ky: f64, center: Point) -> Shape {
+        self.as_shape().skew(kx, ky, center)
+    }
+
+    fn mirror(&self, a: Point, b: Point) -> Shape {
+        self.as_shape().mirror(a, b)
+    }
+
+    /// Returns strokes that fill the inside of the shape with the given
+    /// pattern, not including the shape's own outline. Holes are respected,
+    /// and open subpaths are treated as if they were closed.
+    fn fill(&self, fill: Fill) -> Path {
+        let mut path = fill.strokes(&self.as_bezpath());
+        path.stroke_width = self.stroke();
+        path
+    }
+
     fn intersections(&self, other: &dyn Shaped) -> Vec<Point> {
         if self
             .bounding_box()
@@ -214,9 +475,8 @@ pub trait Shaped {
         nearest_info.2.eval(nearest_info.1)
     }
 
-    // TODO: doesn't adequately report if a path should be closed or not.
-    // to fix this, either explicitly re-add the first point to the end
-    // of the vec, or include some flag in the return value
+    /// Flattens each subpath into a list of points. Closed subpaths repeat
+    /// their first point at the end.
     fn to_points(&self) -> Vec<Vec<Point>> {
         let straightened_paths = self.to_path().separate().unwrap();
         let mut point_groups = vec![];
@@ -225,6 +485,7 @@ pub trait Shaped {
             for cmd in p.flattened().unwrap().commands() {
                 match cmd {
                     PathEl::MoveTo(p) | PathEl::LineTo(p) => points.push(*p),
+                    PathEl::ClosePath if !points.is_empty() => points.push(points[0]),
                     _ => {}
                 }
             }
@@ -0,0 +1,260 @@
+use super::boolean::{boolean_rings, resolve_rings, rings, rings_to_path, BooleanOp};
+use super::region::{polylines, split_subpaths, Region};
+use super::{Path, Point, Vec2, DEFAULT_TOLERANCE};
+use kurbo::BezPath;
+use std::f64::consts::TAU;
+
+/// The fewest segments used to approximate a full circle's worth of round
+/// joins, however small the offset
+const MIN_ARC_SEGMENTS: usize = 16;
+
+/// The longest a miter join may reach from its corner, as a multiple of the
+/// offset distance, before it is beveled instead. This matches SVG's default
+/// `stroke-miterlimit`.
+const MITER_LIMIT: f64 = 4.;
+
+/// How the offsets of two edges are connected around the outside of the
+/// corner between them. At the ends of open paths, `Round` joins form round
+/// caps, and the others cut the ends off square.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Join {
+    /// Extends both edges until they meet, beveling corners so sharp that
+    /// they would reach more than four times the offset distance
+    Miter,
+    /// Bridges the edges with an arc around the corner
+    Round,
+    /// Bridges the edges with a straight line
+    Bevel,
+}
+
+/// Offsets the closed subpaths of `path` by `distance`, and outlines the area
+/// within `distance` of its open subpaths
+pub(crate) fn offset(path: &BezPath, distance: f64, join: Join) -> Path {
+    let (closed, open) = split_subpaths(path);
+    let area = Region::from_bezpath(&closed);
+    let grown = offset_rings(area.rings(), distance, join);
+    let strokes = stroke_polylines(&polylines(&open), distance.abs(), join);
+    if strokes.is_empty() {
+        rings_to_path(&grown)
+    } else {
+        rings_to_path(&boolean_rings(&grown, &strokes, BooleanOp::Union))
+    }
+}
+
+/// Outlines the area covered by drawing `path` with a pen `width` wide
+pub(crate) fn stroke_outline(path: &BezPath, width: f64, join: Join) -> Path {
+    let (closed, open) = split_subpaths(path);
+    let half = width.abs() / 2.;
+    let mut bands = stroke_polylines(&polylines(&open), half, join);
+    // Each closed subpath is stroked on its own, so that any overlapping
+    // subpaths are all still outlined
+    for ring in rings(&closed) {
+        let area = boolean_rings(&[ring], &[], BooleanOp::Union);
+        bands.extend(boolean_rings(
+            &offset_rings(&area, half, join),
+            &offset_rings(&area, -half, join),
+            BooleanOp::Difference,
+        ));
+    }
+    rings_to_path(&resolve_rings(&bands, &[], |w, _| w > 0))
+}
+
+/// Offsets the boundary of the area enclosed by `rings` by `distance`,
+/// growing it for positive distances and shrinking it for negative ones.
+///
+/// Every edge is shifted outward, with joins bridging the gaps that open up
+/// around the outside of corners. Where shifted edges overlap instead, the
+/// original vertex is revisited, forming a small backwards loop. Keeping only
+/// the areas that the shifted rings wind positively around then removes those
+/// loops, along with any parts that have collapsed entirely.
+pub(crate) fn offset_rings(rings: &[Vec<Point>], distance: f64, join: Join) -> Vec<Vec<Point>> {
+    let shifted: Vec<Vec<Point>> = rings
+        .iter()
+        .map(|ring| shift_ring(ring, distance, join))
+        .collect();
+    resolve_rings(&shifted, &[], |w, _| w > 0)
+}
+
+/// Outlines the area within `distance` of each polyline.
+///
+/// Each polyline is traced there and back as a ring enclosing no area, so
+/// that shifting it outward surrounds the polyline on both sides, with the
+/// joins at either end forming its caps.
+fn stroke_polylines(polylines: &[Vec<Point>], distance: f64, join: Join) -> Vec<Vec<Point>> {
+    let shifted: Vec<Vec<Point>> = polylines
+        .iter()
+        .filter_map(|line| {
+            let mut line = line.clone();
+            line.dedup();
+            if line.len() < 2 || distance <= 0. {
+                return None;
+            }
+            let back = line[1..line.len() - 1].iter().rev().cloned();
+            let ring: Vec<Point> = line.iter().cloned().chain(back).collect();
+            Some(shift_ring(&ring, distance, join))
+        })
+        .collect();
+    resolve_rings(&shifted, &[], |w, _| w > 0)
+}
+
+/// Shifts each edge of a ring, whose filled side is on its left, outward by
+/// `distance`, joining them as described in `offset_rings`
+fn shift_ring(ring: &[Point], distance: f64, join: Join) -> Vec<Point> {
+    let n = ring.len();
+    let normal = |a: Point, b: Point| {
+        let dir = b - a;
+        Vec2::new(dir.y, -dir.x) * (distance / dir.hypot())
+    };
+    let mut points = vec![];
+    for i in 0..n {
+        let (prev, p, next) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+        let (n0, n1) = (normal(prev, p), normal(p, next));
+        if (p - prev).cross(next - p) * distance < 0. {
+            points.extend([p + n0, p, p + n1]);
+            continue;
+        }
+        match join {
+            Join::Miter => {
+                let cos = n0.dot(n1) / (distance * distance);
+                if (2. / (1. + cos)).sqrt() <= MITER_LIMIT {
+                    points.push(p + (n0 + n1) / (1. + cos));
+                } else {
+                    points.extend([p + n0, p + n1]);
+                }
+            }
+            Join::Round => points.extend(arc(p, n0, n1, p - prev)),
+            Join::Bevel => points.extend([p + n0, p + n1]),
+        }
+    }
+    points
+}
+
+/// Returns points along the shorter arc around `center` from `center + from`
+/// to `center + to`. Arcs that turn by exactly half a circle, as at the ends
+/// of open paths, bulge in the direction of `heading`.
+fn arc(center: Point, from: Vec2, to: Vec2, heading: Vec2) -> Vec<Point> {
+    let radius = from.hypot();
+    let th0 = from.atan2();
+    let mut sweep = to.atan2() - th0;
+    if sweep > TAU / 2. {
+        sweep -= TAU;
+    } else if sweep < -TAU / 2. {
+        sweep += TAU;
+    }
+    if (sweep.abs() - TAU / 2.).abs() < 1e-9 {
+        sweep = (TAU / 2.).copysign(Vec2::new(-from.y, from.x).dot(heading));
+    }
+
+    // Segments stay within the tolerance of the true arc
+    let mut max_step = TAU / MIN_ARC_SEGMENTS as f64;
+    if radius > DEFAULT_TOLERANCE {
+        max_step = max_step.min(2. * (1. - DEFAULT_TOLERANCE / radius).acos());
+    }
+    let steps = ((sweep.abs() / max_step).ceil() as usize).max(1);
+    (0..=steps)
+        .map(|s| center + Vec2::from_angle(th0 + sweep * s as f64 / steps as f64) * radius)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line, Poly, Shaped, TextBuilder};
+
+    fn square(size: f64) -> Poly {
+        Poly::new(&[
+            point(0, 0),
+            point(size, 0),
+            point(size, size),
+            point(0, size),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn joins() {
+        let square = square(10.);
+        let mitered = square.offset(1., Join::Miter);
+        assert!((mitered.area().abs() - 144.).abs() < 1e-6);
+        assert_eq!(mitered.separate().unwrap().len(), 1);
+
+        let beveled = square.offset(1., Join::Bevel);
+        assert!((beveled.area().abs() - (144. - 4. * 0.5)).abs() < 1e-6);
+
+        let rounded = square.offset(1., Join::Round);
+        let expected = 100. + 4. * 10. + TAU / 2.;
+        assert!((rounded.area().abs() - expected).abs() < 5e-2);
+        assert!(rounded.contains(point(-0.5, -0.5)));
+        assert!(!rounded.contains(point(-0.8, -0.8)));
+
+        // Insets are the same whichever join is used, as square corners only
+        // overlap when shrunk
+        for join in [Join::Miter, Join::Round, Join::Bevel] {
+            assert!((square.offset(-2., join).area().abs() - 36.).abs() < 1e-6);
+        }
+        assert!(square.offset(-6., Join::Round).inner().is_empty());
+    }
+
+    #[test]
+    fn curves_and_splits() {
+        let circle = Circle::new((0., 0.), 10.);
+        let grown = circle.offset(2., Join::Round);
+        for p in grown.to_points().iter().flatten() {
+            assert!((p.distance(Point::ZERO) - 12.).abs() < 2e-2);
+        }
+
+        let blob = Poly::new_smooth(&[point(0, 0), point(20, 5), point(25, 25), point(3, 18)]);
+        let shrunk = blob.offset(-2., Join::Miter);
+        for p in shrunk.to_points().iter().flatten() {
+            assert!(blob.contains(*p));
+            assert!((p.distance(blob.closest_point(*p)) - 2.).abs() < 2e-2);
+        }
+
+        // A dumbbell splits in two when inset past the width of its handle
+        let mut dumbbell = square(10.).to_path();
+        dumbbell.append(&square(10.).translate(Vec2::new(12., 0.)).to_path());
+        let handle = Poly::new(&[point(9, 4), point(13, 4), point(13, 6), point(9, 6)]).unwrap();
+        let dumbbell = dumbbell.union(&handle);
+        assert_eq!(
+            dumbbell.offset(-0.5, Join::Miter).separate().unwrap().len(),
+            1
+        );
+        assert_eq!(
+            dumbbell.offset(-2., Join::Miter).separate().unwrap().len(),
+            2
+        );
+
+        // Growing a ring shrinks its hole until it closes
+        let framed = square(10.).boolean(&Circle::new((5., 5.), 2.), BooleanOp::Difference);
+        assert_eq!(framed.offset(1., Join::Round).separate().unwrap().len(), 2);
+        assert_eq!(framed.offset(3., Join::Round).separate().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn open_paths() {
+        let line = Line::new(point(0, 0), point(10, 0)).unwrap();
+        let butt = line.offset(1., Join::Miter);
+        assert!((butt.area().abs() - 20.).abs() < 1e-6);
+        let round = line.offset(-1., Join::Round);
+        assert!((round.area().abs() - (20. + TAU / 2.)).abs() < 5e-2);
+
+        // Outlining a closed shape's stroke gives a band along its edge
+        let mut square = square(10.);
+        square.set_stroke_width(2.);
+        let band = square.stroke_outline(Join::Miter);
+        assert_eq!(band.separate().unwrap().len(), 2);
+        assert!((band.area().abs() - (144. - 64.)).abs() < 1e-6);
+        assert!(band.contains(point(0.5, 5.)) && !band.contains(point(5., 5.)));
+
+        // Single-line text outlines into a halo around its strokes
+        let text = TextBuilder::new()
+            .single_line()
+            .text_line("nightgraph")
+            .build()
+            .unwrap();
+        let halo = text.offset(3., Join::Round);
+        for p in text.to_points().iter().flatten() {
+            assert!(halo.contains(*p));
+        }
+    }
+}
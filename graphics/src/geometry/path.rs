@@ -354,6 +354,15 @@ impl Path {
             stroke_width: self.stroke_width,
         }
     }
+
+    pub fn transform(&self, affine: Affine) -> Self {
+        let inner = affine * &self.inner;
+        Self {
+            bounding_box: self.bounding_box.map(|_| inner.bounding_box()),
+            inner,
+            stroke_width: self.stroke_width,
+        }
+    }
 }
 
 impl Shaped for Path {
@@ -386,11 +395,22 @@ impl Shaped for Path {
     }
 }
 
+/// Returns the control values of a spline with continuous curvature through
+/// `values`, one coordinate of the knots of a path. The first and second
+/// control values of the curve ending at each knot after the first are
+/// returned separately, so are one shorter than `values`.
 // From http://www.particleincell.com/2012/bezier-splines/
 // Permission with attribution granted in example's source:
 // http://www.particleincell.com/wp-content/uploads/2012/06/circles.svg
 #[allow(clippy::many_single_char_names)]
-fn smoothing_control_values(values: &[f64]) -> (Vec<f64>, Vec<f64>) {
+pub fn smoothing_control_values(values: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    if values.len() < 3 {
+        // A single curve between two knots is straight
+        let thirds = values.windows(2);
+        return thirds
+            .map(|v| (v[0] + (v[1] - v[0]) / 3., v[0] + (v[1] - v[0]) * 2. / 3.))
+            .unzip();
+    }
     let n = values.len() - 1;
     let mut p1 = vec![0.; n];
     let mut p2 = vec![0.; n];
@@ -446,7 +466,7 @@ fn smoothing_control_values(values: &[f64]) -> (Vec<f64>, Vec<f64>) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::geometry::point;
+    use crate::geometry::{point, Line};
     #[test]
     fn path_builder_closed() {
         let points = vec![point(0, 0), point(2, 0), point(2, 2), point(0, 2)];
@@ -458,6 +478,23 @@ mod tests {
         assert!(built_path.perimeter() > built_path_no_close.perimeter(),);
     }
 
+    #[test]
+    fn to_points_closes_subpaths() {
+        let points = vec![point(0, 0), point(4, 0), point(4, 4)];
+        let closed = PathBuilder::new().points(&points).closed().build().unwrap();
+        let open = PathBuilder::new().points(&points).build().unwrap();
+        assert_eq!(
+            closed.to_points(),
+            vec![[&points[..], &points[..1]].concat()]
+        );
+        assert_eq!(open.to_points(), vec![points.clone()]);
+
+        // Only the closed path's closing edge crosses the line
+        let line = Line::new(point(0, 2), point(2, 1)).unwrap();
+        assert!(line.intersections(&open).is_empty());
+        assert_eq!(line.intersections(&closed).len(), 1);
+    }
+
     #[test]
     fn path_builder_precompute() {
         let points = vec![point(0, 0), point(2, 0), point(2, 2), point(0, 2)];
@@ -471,4 +508,31 @@ mod tests {
         assert!(matches!(built_path.bounding_box, Some(_bb)));
         assert!(matches!(built_path_no_precompute.bounding_box, None));
     }
+
+    #[test]
+    fn path_transforms() {
+        let points = vec![point(0, 0), point(2, 0), point(2, 1)];
+        let path = PathBuilder::new().points(&points).build().unwrap();
+        let near = |a: Point, b: Point| (a - b).hypot() < 1e-9;
+        let last = |s: Shape| match s.to_path().inner().elements().last() {
+            Some(PathEl::LineTo(p)) => *p,
+            _ => unreachable!(),
+        };
+
+        let rotated = path.rotate_about(std::f64::consts::FRAC_PI_2, point(1, 0));
+        assert!(near(last(rotated), point(0, 1)));
+
+        let scaled = path.scale_about(2., 3., point(2, 0));
+        assert!(near(last(scaled), point(2, 3)));
+
+        let skewed = path.skew(1., 0., point(0, 0));
+        assert!(near(last(skewed), point(3, 1)));
+
+        let mirrored = path.mirror(point(0, 0), point(1, 1));
+        assert!(near(last(mirrored), point(1, 2)));
+
+        let thick = path.transform_with_stroke(Affine::scale(2.));
+        assert!((thick.stroke() - 2. * path.stroke()).abs() < 1e-12);
+        assert!((path.transform(Affine::scale(2.)).stroke() - path.stroke()).abs() < 1e-12);
+    }
 }
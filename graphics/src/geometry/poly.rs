@@ -118,6 +118,18 @@ impl Poly {
             stroke_width: self.stroke_width,
         }
     }
+
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        self.stroke_width = stroke_width;
+    }
+
+    pub fn transform(&self, affine: Affine) -> Self {
+        Self {
+            inner: affine * &self.inner,
+            bounding_box: None,
+            stroke_width: self.stroke_width,
+        }
+    }
 }
 
 impl Shaped for Poly {
@@ -0,0 +1,451 @@
+use super::boolean::{boolean_rings, rings, BooleanOp};
+use super::{Affine, Path, Point, Shape, Shaped, DEFAULT_TOLERANCE};
+use kurbo::{flatten, BezPath, PathEl};
+
+/// Flattens a path into polylines, one per subpath. Closed subpaths end by
+/// returning to their first point.
+pub(crate) fn polylines(path: &BezPath) -> Vec<Vec<Point>> {
+    let mut polylines: Vec<Vec<Point>> = vec![];
+    flatten(path.iter(), DEFAULT_TOLERANCE, |el| match el {
+        PathEl::MoveTo(p) => polylines.push(vec![p]),
+        PathEl::LineTo(p) => polylines.last_mut().unwrap().push(p),
+        PathEl::ClosePath => {
+            let line = polylines.last_mut().unwrap();
+            line.push(line[0]);
+        }
+        _ => unreachable!(),
+    });
+    polylines.retain(|line| line.len() > 1);
+    polylines
+}
+
+/// Flattens a path into the points of each subpath, and whether the subpath
+/// is closed. Unlike `polylines`, closed subpaths don't repeat their first
+/// point.
+pub(crate) fn subpaths(path: &BezPath) -> Vec<(Vec<Point>, bool)> {
+    let mut subpaths: Vec<(Vec<Point>, bool)> = vec![];
+    flatten(path.iter(), DEFAULT_TOLERANCE, |el| match el {
+        PathEl::MoveTo(p) => subpaths.push((vec![p], false)),
+        PathEl::LineTo(p) => subpaths.last_mut().unwrap().0.push(p),
+        PathEl::ClosePath => {
+            let (points, closed) = subpaths.last_mut().unwrap();
+            if points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            *closed = true;
+        }
+        _ => unreachable!(),
+    });
+    subpaths.retain(|(points, _)| points.len() > 1);
+    subpaths
+}
+
+/// Splits a path into its closed subpaths and its open ones
+pub(crate) fn split_subpaths(path: &BezPath) -> (BezPath, BezPath) {
+    let mut closed = BezPath::new();
+    let mut open = BezPath::new();
+    let mut subpath: Vec<PathEl> = vec![];
+    let mut finish = |subpath: &mut Vec<PathEl>| {
+        let target = match subpath.last() {
+            Some(PathEl::ClosePath) => &mut closed,
+            _ => &mut open,
+        };
+        for el in subpath.drain(..) {
+            target.push(el);
+        }
+    };
+    for el in path.iter() {
+        if let PathEl::MoveTo(_) = el {
+            finish(&mut subpath);
+        }
+        subpath.push(el);
+    }
+    finish(&mut subpath);
+    (closed, open)
+}
+
+/// Buckets items into bands along one axis by their `(min, max)` extent on
+/// that axis, so that queries only visit items that can possibly be hit.
+#[derive(Clone)]
+pub(crate) struct BandIndex {
+    min: f64,
+    max: f64,
+    band_size: f64,
+    bands: Vec<Vec<usize>>,
+}
+
+impl BandIndex {
+    pub(crate) fn new(extents: &[(f64, f64)]) -> Self {
+        let (min, max) = extents.iter().fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), (lo, hi)| (min.min(*lo), max.max(*hi)),
+        );
+        let band_count = ((extents.len() as f64).sqrt().ceil() as usize).max(1);
+        let band_size = ((max - min) / band_count as f64).max(f64::MIN_POSITIVE);
+        let mut bands = vec![vec![]; band_count];
+        for (i, (lo, hi)) in extents.iter().enumerate() {
+            let first = (((lo - min) / band_size) as usize).min(band_count - 1);
+            let last = (((hi - min) / band_size) as usize).min(band_count - 1);
+            for band in &mut bands[first..=last] {
+                band.push(i);
+            }
+        }
+        Self {
+            min,
+            max,
+            band_size,
+            bands,
+        }
+    }
+
+    fn band(&self, v: f64) -> usize {
+        (((v - self.min) / self.band_size) as usize).min(self.bands.len() - 1)
+    }
+
+    /// Returns the items whose extent may contain `v`
+    pub(crate) fn query(&self, v: f64) -> &[usize] {
+        if v < self.min || v > self.max {
+            return &[];
+        }
+        &self.bands[self.band(v)]
+    }
+
+    /// Returns the items whose extent may overlap `lo..=hi`. Items spanning
+    /// several bands are yielded once per band.
+    pub(crate) fn query_range(&self, lo: f64, hi: f64) -> impl Iterator<Item = &usize> {
+        let bands = if hi < self.min || lo > self.max || self.bands.is_empty() {
+            &self.bands[0..0]
+        } else {
+            &self.bands[self.band(lo.max(self.min))..=self.band(hi.min(self.max))]
+        };
+        bands.iter().flatten()
+    }
+}
+
+/// Buckets items into a uniform grid of cells by their bounding boxes, so
+/// that only items sharing a cell need to be compared with each other.
+pub(crate) struct GridIndex {
+    min: Point,
+    cell_size: f64,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl GridIndex {
+    /// Creates an index of roughly one cell per item over the combined
+    /// extent of `bounds`, given as `(min, max)` corner pairs.
+    pub(crate) fn new(bounds: &[(Point, Point)]) -> Self {
+        let (min, max) = bounds.iter().fold(
+            (
+                Point::new(f64::INFINITY, f64::INFINITY),
+                Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(min, max), (lo, hi)| {
+                (
+                    Point::new(min.x.min(lo.x), min.y.min(lo.y)),
+                    Point::new(max.x.max(hi.x), max.y.max(hi.y)),
+                )
+            },
+        );
+        let extent = if bounds.is_empty() {
+            kurbo::Size::ZERO
+        } else {
+            (max - min).to_size()
+        };
+        let n = bounds.len().max(1) as f64;
+        let cell_size = (extent.width * extent.height / n)
+            .sqrt()
+            .max(extent.width.max(extent.height) / n)
+            .max(f64::MIN_POSITIVE);
+        let cols = (extent.width / cell_size) as usize + 1;
+        let rows = (extent.height / cell_size) as usize + 1;
+
+        let mut grid = Self {
+            min,
+            cell_size,
+            cols,
+            rows,
+            cells: vec![vec![]; cols * rows],
+        };
+        for (i, (lo, hi)) in bounds.iter().enumerate() {
+            for cell in grid.cells_overlapping(*lo, *hi) {
+                grid.cells[cell].push(i);
+            }
+        }
+        grid
+    }
+
+    fn col_row(&self, p: Point) -> (usize, usize) {
+        let col = ((p.x - self.min.x) / self.cell_size).max(0.) as usize;
+        let row = ((p.y - self.min.y) / self.cell_size).max(0.) as usize;
+        (col.min(self.cols - 1), row.min(self.rows - 1))
+    }
+
+    /// Returns the index of the cell containing `p`, clamped to the grid
+    pub(crate) fn cell(&self, p: Point) -> usize {
+        let (col, row) = self.col_row(p);
+        row * self.cols + col
+    }
+
+    /// Returns the indices of all cells overlapping the box `lo`..`hi`
+    pub(crate) fn cells_overlapping(&self, lo: Point, hi: Point) -> impl Iterator<Item = usize> {
+        let (col_lo, row_lo) = self.col_row(lo);
+        let (col_hi, row_hi) = self.col_row(hi);
+        let cols = self.cols;
+        (row_lo..=row_hi).flat_map(move |row| (col_lo..=col_hi).map(move |col| row * cols + col))
+    }
+
+    /// Returns every item overlapping a cell that the box `lo`..`hi`
+    /// overlaps, each once, in order
+    pub(crate) fn query(&self, lo: Point, hi: Point) -> Vec<usize> {
+        let mut items: Vec<usize> = self
+            .cells_overlapping(lo, hi)
+            .flat_map(|cell| self.cells[cell].iter().copied())
+            .collect();
+        items.sort_unstable();
+        items.dedup();
+        items
+    }
+
+    /// Returns the total number of cells in the grid
+    pub(crate) fn cells_len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns every pair of items `(i, j)` with `i < j` that share a cell,
+    /// each exactly once.
+    pub(crate) fn candidate_pairs(&self, bounds: &[(Point, Point)]) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for (cell, items) in self.cells.iter().enumerate() {
+            for (n, &i) in items.iter().enumerate() {
+                for &j in &items[n + 1..] {
+                    // Only report a pair from the first cell both items share
+                    let corner = Point::new(
+                        bounds[i].0.x.max(bounds[j].0.x),
+                        bounds[i].0.y.max(bounds[j].0.y),
+                    );
+                    if self.cell(corner) == cell {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
+
+/// A filled area bounded by flattened rings, indexed for fast point and
+/// scanline queries. Rings wind with the filled side on their left.
+#[derive(Clone)]
+pub(crate) struct Region {
+    rings: Vec<Vec<Point>>,
+    edges: Vec<(Point, Point)>,
+    index: BandIndex,
+}
+
+impl Region {
+    pub(crate) fn new(rings: Vec<Vec<Point>>) -> Self {
+        let edges: Vec<(Point, Point)> = rings
+            .iter()
+            .flat_map(|ring| {
+                ring.iter()
+                    .zip(ring.iter().cycle().skip(1))
+                    .map(|(a, b)| (*a, *b))
+            })
+            .collect();
+        let index = BandIndex::new(
+            &edges
+                .iter()
+                .map(|(a, b)| (a.y.min(b.y), a.y.max(b.y)))
+                .collect::<Vec<_>>(),
+        );
+        Self {
+            rings,
+            edges,
+            index,
+        }
+    }
+
+    /// Builds the region filled by `path` under the nonzero rule, resolving
+    /// any self-intersections and normalizing ring winding
+    pub(crate) fn from_bezpath(path: &BezPath) -> Self {
+        Self::new(boolean_rings(&rings(path), &[], BooleanOp::Union))
+    }
+
+    /// Returns the area covered by both `self` and `other`
+    pub(crate) fn intersection(&self, other: &Region) -> Self {
+        Self::new(boolean_rings(
+            &self.rings,
+            &other.rings,
+            BooleanOp::Intersection,
+        ))
+    }
+
+    pub(crate) fn transform(&self, affine: Affine) -> Self {
+        Self::new(
+            self.rings
+                .iter()
+                .map(|ring| ring.iter().map(|p| affine * *p).collect())
+                .collect(),
+        )
+    }
+
+    pub(crate) fn rings(&self) -> &[Vec<Point>] {
+        &self.rings
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rings.is_empty()
+    }
+
+    /// Returns the x coordinates at which the horizontal line at `y` crosses
+    /// the boundary, sorted, along with the change in winding at each.
+    pub(crate) fn crossings(&self, y: f64) -> Vec<(f64, i32)> {
+        let mut crossings: Vec<(f64, i32)> = self
+            .index
+            .query(y)
+            .iter()
+            .filter_map(|&i| {
+                let (p, q) = self.edges[i];
+                if (p.y <= y) != (q.y <= y) {
+                    let x = p.x + (y - p.y) * (q.x - p.x) / (q.y - p.y);
+                    Some((x, if q.y > p.y { -1 } else { 1 }))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        crossings
+    }
+
+    /// Returns the spans of the horizontal line at `y` that lie inside the
+    /// region, ordered by increasing x
+    pub(crate) fn spans(&self, y: f64) -> Vec<(f64, f64)> {
+        let mut spans = vec![];
+        let mut winding = 0;
+        let mut span_start = 0.;
+        for (x, delta) in self.crossings(y) {
+            let was_inside = winding != 0;
+            winding += delta;
+            match (was_inside, winding != 0) {
+                (false, true) => span_start = x,
+                (true, false) if x > span_start => spans.push((span_start, x)),
+                _ => {}
+            }
+        }
+        spans
+    }
+
+    pub(crate) fn contains(&self, p: Point) -> bool {
+        let winding: i32 = self
+            .crossings(p.y)
+            .iter()
+            .filter(|(x, _)| *x > p.x)
+            .map(|(_, delta)| delta)
+            .sum();
+        winding != 0
+    }
+
+    /// Returns the parameters along `a`→`b` at which it crosses the boundary
+    fn boundary_params(&self, a: Point, b: Point) -> Vec<f64> {
+        let r = b - a;
+        let mut ts: Vec<f64> = self
+            .index
+            .query_range(a.y.min(b.y), a.y.max(b.y))
+            .filter_map(|&i| {
+                let (p, q) = self.edges[i];
+                let s = q - p;
+                let denom = r.cross(s);
+                if denom == 0. {
+                    return None;
+                }
+                let t = (p - a).cross(s) / denom;
+                let u = (p - a).cross(r) / denom;
+                if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u) {
+                    Some(t)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        ts.dedup();
+        ts
+    }
+
+    /// Returns true if the straight line `a`→`b` passes through the boundary
+    /// anywhere other than at its endpoints
+    pub(crate) fn crosses(&self, a: Point, b: Point) -> bool {
+        const END_MARGIN: f64 = 1e-9;
+        self.boundary_params(a, b)
+            .iter()
+            .any(|t| *t > END_MARGIN && *t < 1. - END_MARGIN)
+    }
+
+    /// Splits a polyline where it crosses the boundary, keeping the pieces
+    /// inside (or outside, when `inside` is false) the region
+    pub(crate) fn clip_polyline(&self, points: &[Point], inside: bool) -> Vec<Vec<Point>> {
+        let mut pieces = vec![];
+        let mut current: Vec<Point> = vec![];
+        for (&a, &b) in points.iter().zip(points.iter().skip(1)) {
+            let mut ts = vec![0.];
+            ts.extend(self.boundary_params(a, b));
+            ts.push(1.);
+            for (t0, t1) in ts.iter().zip(ts.iter().skip(1)) {
+                if t1 - t0 <= 0. {
+                    continue;
+                }
+                let p0 = a.lerp(b, *t0);
+                let p1 = a.lerp(b, *t1);
+                if self.contains(p0.midpoint(p1)) == inside {
+                    if current.last() != Some(&p0) {
+                        if current.len() > 1 {
+                            pieces.push(std::mem::take(&mut current));
+                        }
+                        current = vec![p0];
+                    }
+                    current.push(p1);
+                } else if current.len() > 1 {
+                    pieces.push(std::mem::take(&mut current));
+                } else {
+                    current.clear();
+                }
+            }
+        }
+        if current.len() > 1 {
+            pieces.push(current);
+        }
+        pieces
+    }
+
+    /// Trims the stroke of `shape` to the parts lying inside the region (or
+    /// outside, when `inside` is false), returning `None` if nothing remains.
+    /// Shapes that are kept entirely are returned unchanged, while those
+    /// crossing the boundary are flattened into a path.
+    pub(crate) fn clip_shape(&self, shape: &Shape, inside: bool) -> Option<Shape> {
+        let mut unchanged = true;
+        let mut clipped = BezPath::new();
+        for line in polylines(&shape.to_path().as_bezpath()) {
+            let pieces = self.clip_polyline(&line, inside);
+            unchanged &= pieces.len() == 1 && pieces[0] == line;
+            for piece in pieces {
+                clipped.move_to(piece[0]);
+                for p in piece.iter().skip(1) {
+                    clipped.line_to(*p);
+                }
+            }
+        }
+
+        if unchanged {
+            Some(shape.clone())
+        } else if clipped.elements().is_empty() {
+            None
+        } else {
+            let mut path = Path::from(clipped);
+            path.stroke_width = shape.stroke();
+            Some(Shape::Path(path))
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use super::path::smoothing_control_values;
+use super::region::subpaths;
+use super::{point, Path, PathEl, Point};
+use kurbo::BezPath;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Ways of removing vertices from a path that contribute little to its
+/// shape, such as those of noisy or densely sampled lines.
+///
+/// All distances are in canvas units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Simplify {
+    /// Ramer–Douglas–Peucker, which keeps the vertices that stray more than
+    /// `tolerance` from the line between the vertices kept around them
+    DouglasPeucker { tolerance: f64 },
+    /// Visvalingam–Whyatt, which repeatedly drops the vertex forming the
+    /// smallest triangle with its neighbours, until none are smaller than
+    /// `min_area`
+    Visvalingam { min_area: f64 },
+}
+
+/// Ways of rounding off the corners of a path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Smooth {
+    /// Chaikin's corner cutting, which replaces each corner with two points a
+    /// quarter of the way along its edges, `iterations` times over
+    Chaikin { iterations: usize },
+    /// Curves through every vertex, each leaving in the direction between
+    /// the vertices on either side of it
+    CatmullRom,
+    /// Curves through every vertex with a continuous curvature, as used by
+    /// `Path::from_points_smooth`
+    Spline,
+}
+
+/// Simplifies each subpath of `path` after flattening it, keeping at least
+/// the ends of open subpaths and three vertices of closed ones
+pub(crate) fn simplify(path: &BezPath, method: Simplify) -> Path {
+    let mut simplified = BezPath::new();
+    for (points, closed) in subpaths(path) {
+        let points = match method {
+            Simplify::DouglasPeucker { tolerance } => douglas_peucker(&points, closed, tolerance),
+            Simplify::Visvalingam { min_area } => visvalingam(&points, closed, min_area),
+        };
+        push_polyline(&mut simplified, &points, closed);
+    }
+    simplified.into()
+}
+
+/// Smooths each subpath of `path` after flattening it
+pub(crate) fn smooth(path: &BezPath, method: Smooth) -> Path {
+    let mut smoothed = BezPath::new();
+    for (points, closed) in subpaths(path) {
+        match method {
+            Smooth::Chaikin { iterations } => {
+                let points = (0..iterations).fold(points, |p, _| chaikin(&p, closed));
+                push_polyline(&mut smoothed, &points, closed);
+            }
+            Smooth::CatmullRom => smoothed.extend(catmull_rom(&points, closed)),
+            Smooth::Spline => smoothed.extend(spline(&points, closed)),
+        }
+    }
+    smoothed.into()
+}
+
+fn push_polyline(path: &mut BezPath, points: &[Point], closed: bool) {
+    path.move_to(points[0]);
+    for p in &points[1..] {
+        path.line_to(*p);
+    }
+    if closed {
+        path.close_path();
+    }
+}
+
+/// Returns the distance from `p` to the segment from `a` to `b`
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len2 = ab.hypot2();
+    if len2 == 0. {
+        return p.distance(a);
+    }
+    let t = ((p - a).dot(ab) / len2).clamp(0., 1.);
+    p.distance(a + ab * t)
+}
+
+fn douglas_peucker(points: &[Point], closed: bool, tolerance: f64) -> Vec<Point> {
+    let n = points.len();
+    let mut keep = vec![false; n];
+    let mut ranges = vec![];
+    keep[0] = true;
+    let far = if closed {
+        // Closed subpaths are split in two at the vertex farthest from the
+        // first, with the range ending back at the first vertex
+        let far = (1..n)
+            .max_by(|a, b| {
+                let (da, db) = (
+                    points[*a].distance(points[0]),
+                    points[*b].distance(points[0]),
+                );
+                da.partial_cmp(&db).unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+        ranges.extend([(0, far), (far, n)]);
+        far
+    } else {
+        ranges.push((0, n - 1));
+        n - 1
+    };
+    keep[far] = true;
+
+    let farthest = |start: usize, end: usize| {
+        let (a, b) = (points[start], points[end % n]);
+        (start + 1..end)
+            .map(|i| (i, segment_distance(points[i], a, b)))
+            .max_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal))
+    };
+    while let Some((start, end)) = ranges.pop() {
+        if let Some((i, distance)) = farthest(start, end) {
+            if distance > tolerance {
+                keep[i] = true;
+                ranges.extend([(start, i), (i, end)]);
+            }
+        }
+    }
+
+    // Closed subpaths keep at least three vertices, so they still enclose an
+    // area
+    if closed && keep.iter().filter(|k| **k).count() < 3 {
+        let candidates = [farthest(0, far), farthest(far, n)];
+        let best = candidates
+            .iter()
+            .flatten()
+            .max_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal));
+        if let Some((i, _)) = best {
+            keep[*i] = true;
+        }
+    }
+    (0..n).filter(|i| keep[*i]).map(|i| points[i]).collect()
+}
+
+/// A vertex that may be removed, ordered so that the smallest triangle is
+/// popped first from a `BinaryHeap`
+struct Candidate {
+    area: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .partial_cmp(&self.area)
+            .unwrap_or(Ordering::Equal)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+fn visvalingam(points: &[Point], closed: bool, min_area: f64) -> Vec<Point> {
+    let n = points.len();
+    let min_len = if closed { 3 } else { 2 };
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut removed = vec![false; n];
+    let mut areas = vec![f64::INFINITY; n];
+    let removable = |i: usize| closed || (i != 0 && i != n - 1);
+
+    let triangle = |a: Point, b: Point, c: Point| ((b - a).cross(c - a) / 2.).abs();
+    let mut heap = BinaryHeap::new();
+    for i in (0..n).filter(|i| removable(*i)) {
+        areas[i] = triangle(points[prev[i]], points[i], points[next[i]]);
+        heap.push(Candidate {
+            area: areas[i],
+            index: i,
+        });
+    }
+
+    let mut remaining = n;
+    while let Some(Candidate { area, index }) = heap.pop() {
+        if removed[index] || area != areas[index] {
+            continue;
+        }
+        if area >= min_area || remaining <= min_len {
+            break;
+        }
+        removed[index] = true;
+        remaining -= 1;
+        let (p, q) = (prev[index], next[index]);
+        next[p] = q;
+        prev[q] = p;
+        for i in [p, q] {
+            if removable(i) {
+                // Neighbours never rank below the vertex just removed, so
+                // that removal proceeds in order of significance
+                areas[i] = triangle(points[prev[i]], points[i], points[next[i]]).max(area);
+                heap.push(Candidate {
+                    area: areas[i],
+                    index: i,
+                });
+            }
+        }
+    }
+    (0..n).filter(|i| !removed[*i]).map(|i| points[i]).collect()
+}
+
+fn chaikin(points: &[Point], closed: bool) -> Vec<Point> {
+    let n = points.len();
+    let edges = if closed { n } else { n - 1 };
+    let mut cut = vec![];
+    if !closed {
+        cut.push(points[0]);
+    }
+    for i in 0..edges {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        cut.push(a.lerp(b, 0.25));
+        cut.push(a.lerp(b, 0.75));
+    }
+    if !closed {
+        cut.push(points[n - 1]);
+    }
+    cut
+}
+
+fn catmull_rom(points: &[Point], closed: bool) -> Vec<PathEl> {
+    let n = points.len();
+    // Open ends continue in the direction of their only neighbour
+    let at = |i: isize| {
+        if closed {
+            points[i.rem_euclid(n as isize) as usize]
+        } else {
+            points[i.clamp(0, n as isize - 1) as usize]
+        }
+    };
+    let segments = if closed { n } else { n - 1 };
+    let mut cmds = vec![PathEl::MoveTo(points[0])];
+    for i in 0..segments as isize {
+        let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+        cmds.push(PathEl::CurveTo(
+            p1 + (p2 - p0) / 6.,
+            p2 - (p3 - p1) / 6.,
+            p2,
+        ));
+    }
+    if closed {
+        cmds.push(PathEl::ClosePath);
+    }
+    cmds
+}
+
+fn spline(points: &[Point], closed: bool) -> Vec<PathEl> {
+    if points.len() < 3 {
+        let mut cmds = vec![PathEl::MoveTo(points[0])];
+        cmds.extend(points[1..].iter().map(|p| PathEl::LineTo(*p)));
+        if closed {
+            cmds.push(PathEl::ClosePath);
+        }
+        return cmds;
+    }
+    if closed {
+        return Path::from_points_smooth_closed(points).commands().to_vec();
+    }
+    let xs: Vec<f64> = points.iter().map(|p| p.x).collect();
+    let ys: Vec<f64> = points.iter().map(|p| p.y).collect();
+    let (cp1_xs, cp2_xs) = smoothing_control_values(&xs);
+    let (cp1_ys, cp2_ys) = smoothing_control_values(&ys);
+    let mut cmds = vec![PathEl::MoveTo(points[0])];
+    for i in 1..points.len() {
+        let c1 = point(cp1_xs[i - 1], cp1_ys[i - 1]);
+        let c2 = point(cp2_xs[i - 1], cp2_ys[i - 1]);
+        cmds.push(PathEl::CurveTo(c1, c2, points[i]));
+    }
+    cmds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Poly, Shaped};
+
+    fn noisy_line() -> Path {
+        let points: Vec<Point> = (0..=100)
+            .map(|i| point(i, if i % 2 == 0 { 0.1 } else { -0.1 }))
+            .collect();
+        Path::from_points(&points)
+    }
+
+    fn noisy_circle() -> Poly {
+        let points: Vec<Point> = (0..200)
+            .map(|i| {
+                let angle = i as f64 / 200. * std::f64::consts::TAU;
+                let r = 50. + if i % 2 == 0 { 0.2 } else { -0.2 };
+                point(r * angle.cos(), r * angle.sin())
+            })
+            .collect();
+        Poly::new(&points).unwrap()
+    }
+
+    #[test]
+    fn douglas_peucker_simplifies() {
+        let line = noisy_line().simplify(Simplify::DouglasPeucker { tolerance: 0.5 });
+        assert_eq!(line.commands().len(), 2);
+        assert_eq!(line.position_at(0.).point, point(0, 0.1));
+
+        let circle = noisy_circle();
+        let simplified = circle.simplify(Simplify::DouglasPeucker { tolerance: 1. });
+        let vertices = simplified.commands().len() - 2;
+        assert!(vertices > 8 && vertices < 40);
+        assert_eq!(simplified.commands().last(), Some(&PathEl::ClosePath));
+        assert!((simplified.area() - circle.area()).abs() / circle.area() < 0.05);
+
+        // Closed shapes keep enough vertices to stay closed
+        let triangle = circle.simplify(Simplify::DouglasPeucker { tolerance: 1000. });
+        assert_eq!(triangle.commands().len(), 4);
+    }
+
+    #[test]
+    fn visvalingam_simplifies() {
+        let line = noisy_line().simplify(Simplify::Visvalingam { min_area: 20. });
+        assert_eq!(line.commands().len(), 2);
+
+        let circle = noisy_circle();
+        let simplified = circle.simplify(Simplify::Visvalingam { min_area: 5. });
+        let vertices = simplified.commands().len() - 2;
+        assert!(vertices > 8 && vertices < 60);
+        assert!((simplified.area() - circle.area()).abs() / circle.area() < 0.05);
+
+        let triangle = circle.simplify(Simplify::Visvalingam { min_area: 1e9 });
+        assert_eq!(triangle.commands().len(), 4);
+    }
+
+    #[test]
+    fn smoothing() {
+        let mut zig_zag = Path::from_points(&[point(0, 0), point(10, 10), point(20, 0)]);
+        zig_zag.append(
+            &Poly::new(&[point(30, 0), point(40, 0), point(40, 10)])
+                .unwrap()
+                .to_path(),
+        );
+
+        for method in [
+            Smooth::Chaikin { iterations: 2 },
+            Smooth::CatmullRom,
+            Smooth::Spline,
+        ] {
+            let smoothed = zig_zag.smooth(method);
+            let subpaths = smoothed.separate().unwrap();
+            assert_eq!(subpaths.len(), 2);
+            // Open ends stay in place, and closed subpaths stay closed
+            assert_eq!(subpaths[0].position_at(0.).point, point(0, 0));
+            assert!(subpaths[0].position_at(1e3).point.distance(point(20, 0)) < 1e-9);
+            assert_eq!(subpaths[1].commands().last(), Some(&PathEl::ClosePath));
+            // Corners are rounded off, but curves still pass through them
+            let corner = smoothed
+                .closest_point(point(10, 10))
+                .distance(point(10, 10));
+            match method {
+                Smooth::Chaikin { .. } => assert!(corner > 1.),
+                _ => assert!(corner < 1e-6),
+            }
+        }
+
+        // Subpaths too short to curve stay closed too
+        let sliver = Path::from_commands(&[
+            PathEl::MoveTo(point(0, 0)),
+            PathEl::LineTo(point(10, 0)),
+            PathEl::ClosePath,
+        ])
+        .unwrap();
+        let smoothed = sliver.smooth(Smooth::Spline);
+        assert_eq!(smoothed.commands().last(), Some(&PathEl::ClosePath));
+
+        let chaikin = zig_zag.smooth(Smooth::Chaikin { iterations: 1 });
+        let points = &chaikin.to_points()[0];
+        assert_eq!(points[1], point(2.5, 2.5));
+        assert_eq!(points[2], point(7.5, 7.5));
+    }
+}
@@ -0,0 +1,239 @@
+use super::{GeomError, GeomResult};
+use kurbo::BezPath;
+use std::collections::HashMap;
+use svg::node::element::tag::Type;
+use svg::parser::Event;
+
+/// A single-line glyph, in font units with the y axis pointing up from the
+/// baseline
+#[derive(Clone)]
+pub(crate) struct StrokeGlyph {
+    pub advance: f64,
+    pub path: BezPath,
+}
+
+/// A single-line font, whose glyphs are centerline strokes rather than
+/// outlines, read from an SVG font such as those used by Inkscape's Hershey
+/// Text extension
+#[derive(Clone)]
+pub struct StrokeFont {
+    units_per_em: f64,
+    ascent: f64,
+    descent: f64,
+    glyphs: HashMap<char, StrokeGlyph>,
+    missing: StrokeGlyph,
+    kerning: HashMap<(char, char), f64>,
+}
+
+impl StrokeFont {
+    /// Reads a font from the contents of an SVG font file
+    pub fn from_svg(content: &str) -> GeomResult<Self> {
+        let mut default_advance = None;
+        let mut units_per_em = 1000.;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut glyphs = HashMap::new();
+        let mut missing = None;
+        let mut kerning = HashMap::new();
+
+        let parser = svg::read(content)?;
+        for event in parser {
+            let (name, attributes) = match event {
+                Event::Tag(name, Type::Start, attributes)
+                | Event::Tag(name, Type::Empty, attributes) => (name, attributes),
+                Event::Error(e) => return Err(GeomError::font_error(&e.to_string())),
+                _ => continue,
+            };
+            let number = |attribute: &str| -> GeomResult<Option<f64>> {
+                match attributes.get(attribute) {
+                    Some(v) => v.trim().parse().map(Some).map_err(|_| {
+                        GeomError::font_error(&format!("malformed {} '{}'", attribute, v))
+                    }),
+                    None => Ok(None),
+                }
+            };
+            let glyph = |advance: Option<f64>| -> GeomResult<StrokeGlyph> {
+                let path = match attributes.get("d") {
+                    Some(d) => BezPath::from_svg(d).map_err(|e| {
+                        GeomError::font_error(&format!("malformed glyph path: {}", e))
+                    })?,
+                    None => BezPath::new(),
+                };
+                Ok(StrokeGlyph {
+                    advance: advance.unwrap_or(0.),
+                    path,
+                })
+            };
+            match name {
+                "font" => default_advance = number("horiz-adv-x")?,
+                "font-face" => {
+                    units_per_em = number("units-per-em")?.unwrap_or(units_per_em);
+                    ascent = number("ascent")?;
+                    descent = number("descent")?;
+                }
+                "missing-glyph" => {
+                    missing = Some(glyph(number("horiz-adv-x")?.or(default_advance))?)
+                }
+                "glyph" => {
+                    // Ligatures and other multi-character glyphs aren't used
+                    let unicode = attributes.get("unicode").map(|u| unescape(u));
+                    if let Some(c) = unicode.as_deref().and_then(single_char) {
+                        glyphs.insert(c, glyph(number("horiz-adv-x")?.or(default_advance))?);
+                    }
+                }
+                "hkern" => {
+                    let chars = |attribute: &str| -> Vec<char> {
+                        let list = attributes.get(attribute).map(|u| unescape(u));
+                        list.iter()
+                            .flat_map(|l| l.split(','))
+                            .filter_map(|u| single_char(u.trim()))
+                            .collect()
+                    };
+                    let k = number("k")?.unwrap_or(0.);
+                    for first in chars("u1") {
+                        for &second in &chars("u2") {
+                            kerning.insert((first, second), k);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if glyphs.is_empty() {
+            return Err(GeomError::font_error("SVG font has no glyphs"));
+        }
+        let ascent = ascent.unwrap_or(units_per_em * 0.8);
+        let missing = missing.unwrap_or(StrokeGlyph {
+            advance: default_advance.unwrap_or(units_per_em / 2.),
+            path: BezPath::new(),
+        });
+        let descent = descent.unwrap_or(ascent - units_per_em);
+        // Glyphs are scaled by the height, so it can't be zero
+        let height = ascent - descent;
+        if height <= 0. || height.is_nan() {
+            return Err(GeomError::font_error("SVG font has zero height"));
+        }
+        Ok(Self {
+            units_per_em,
+            ascent,
+            descent,
+            glyphs,
+            missing,
+            kerning,
+        })
+    }
+
+    /// Returns the single-line font bundled with nightgraphics, covering
+    /// printable ASCII
+    pub fn bundled() -> Self {
+        Self::from_svg(include_str!(
+            "../../../assets/fonts/NightgraphSingleLine.svg"
+        ))
+        .expect("bundled font is valid")
+    }
+
+    /// Returns the number of font units in the height of the font, from its
+    /// descent to its ascent
+    pub fn height(&self) -> f64 {
+        self.ascent - self.descent
+    }
+
+    /// Returns the distance from the baseline to the top of the font, in font
+    /// units
+    pub fn ascent(&self) -> f64 {
+        self.ascent
+    }
+
+    /// Returns the distance from the baseline to the bottom of the font, in
+    /// font units. This is negative for fonts that extend below the baseline.
+    pub fn descent(&self) -> f64 {
+        self.descent
+    }
+
+    /// Returns the number of font units per em
+    pub fn units_per_em(&self) -> f64 {
+        self.units_per_em
+    }
+
+    /// Returns the glyph for a character, or the missing glyph if the font
+    /// doesn't have one
+    pub(crate) fn glyph(&self, c: char) -> &StrokeGlyph {
+        self.glyphs.get(&c).unwrap_or(&self.missing)
+    }
+
+    /// Returns the adjustment to the space between two characters, in font
+    /// units, from the font's kerning pairs
+    pub(crate) fn kerning(&self, first: char, second: char) -> f64 {
+        // A positive `k` brings the glyphs closer together
+        self.kerning.get(&(first, second)).map_or(0., |k| -k)
+    }
+}
+
+/// Returns the only character in a string, if it has exactly one
+fn single_char(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Replaces the XML entities that can appear in a glyph's `unicode`
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_covers_ascii() {
+        let font = StrokeFont::bundled();
+        assert_eq!(font.height(), 1000.);
+        for c in (b' '..=b'~').map(char::from) {
+            assert!(font.glyphs.contains_key(&c), "missing '{}'", c);
+        }
+        assert_eq!(font.glyph('&').advance, 480.);
+        assert!(!font.glyph('\u{2603}').path.elements().is_empty());
+    }
+
+    #[test]
+    fn reads_svg_fonts() {
+        let font = StrokeFont::from_svg(
+            r#"<svg><defs><font horiz-adv-x="300">
+            <font-face units-per-em="21" ascent="12" descent="-9"/>
+            <glyph unicode="l" d="M 4 -9 L 4 12"/>
+            <glyph unicode="ll" d="M 0 0 L 1 1"/>
+            <glyph unicode="-" horiz-adv-x="8"/>
+            <hkern u1="l,-" u2="l" k="2"/>
+            </font></defs></svg>"#,
+        )
+        .unwrap();
+        assert_eq!(font.height(), 21.);
+        assert_eq!(font.glyph('l').advance, 300.);
+        assert_eq!(font.glyph('-').advance, 8.);
+        assert!(font.glyph('-').path.elements().is_empty());
+        assert_eq!(font.glyphs.len(), 2);
+        assert_eq!(font.kerning('-', 'l'), -2.);
+        assert_eq!(font.kerning('l', '-'), 0.);
+
+        assert!(StrokeFont::from_svg("<svg></svg>").is_err());
+        let flat = |face: &str| {
+            StrokeFont::from_svg(&format!(
+                r#"<svg><font>{}<glyph unicode="l" d="M 0 0 L 0 1"/></font></svg>"#,
+                face
+            ))
+        };
+        let zero_height =
+            |face| matches!(flat(face), Err(GeomError::FontError(m)) if m.contains("zero height"));
+        assert!(zero_height(r#"<font-face ascent="0" descent="0"/>"#));
+        assert!(zero_height(r#"<font-face units-per-em="0"/>"#));
+    }
+}
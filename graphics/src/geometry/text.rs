@@ -1,16 +1,53 @@
-use super::{point, GeomError, GeomResult, Path, PathBuilder, PathEl, Point, DEFAULT_STROKE_WIDTH};
-use rusttype::{Font, OutlineBuilder, Scale, Vector};
-use std::fs::File;
-use std::io::BufReader;
-use std::io::Read;
+use super::measure::MeasuredPath;
+use super::{
+    load_font, Affine, Font, GeomResult, Path, PathBuilder, PathEl, Point, Shaped, Vec2,
+    DEFAULT_FONT, DEFAULT_SINGLE_LINE_FONT, DEFAULT_STROKE_WIDTH,
+};
+use kurbo::Rect;
 
+/// How lines of text are placed horizontally
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces of every line but the last of each text line to
+    /// fill the width the text is laid out in
+    Justify,
+}
+
+/// Which height of the first line of text is placed at the origin
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextAnchor {
+    Baseline,
+    Ascender,
+    /// A fraction of the font's ascent above the baseline
+    AscentFraction(f64),
+}
+
+impl Default for TextAnchor {
+    fn default() -> Self {
+        Self::AscentFraction(2. / 3.)
+    }
+}
+
+#[derive(Clone)]
 pub struct TextBuilder<'a> {
     font: Option<&'a str>,
     size: Option<f64>,
     text_lines: Vec<&'a str>,
     line_padding: Option<f64>,
+    line_height: Option<f64>,
     origin: Option<Point>,
     stroke_width: Option<f64>,
+    single_line: bool,
+    align: TextAlign,
+    anchor: TextAnchor,
+    max_width: Option<f64>,
+    tracking: f64,
+    kerning: bool,
+    kern_pairs: Vec<(char, char, f64)>,
+    along: Option<&'a Path>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -21,13 +58,24 @@ impl<'a> TextBuilder<'a> {
             size: None,
             text_lines: vec![],
             line_padding: None,
+            line_height: None,
             origin: None,
             stroke_width: None,
+            single_line: false,
+            align: TextAlign::Left,
+            anchor: TextAnchor::default(),
+            max_width: None,
+            tracking: 0.,
+            kerning: true,
+            kern_pairs: vec![],
+            along: None,
         }
     }
 
-    pub fn font(mut self, font_path: &'a str) -> Self {
-        self.font = Some(font_path);
+    /// Sets the font by the name it was registered with, or the path of a
+    /// font file. See `load_font`.
+    pub fn font(mut self, name: &'a str) -> Self {
+        self.font = Some(name);
         self
     }
 
@@ -46,141 +94,566 @@ impl<'a> TextBuilder<'a> {
         self
     }
 
+    /// Sets the space between the ascent of each line and the baseline of the
+    /// line before it, in canvas units
     pub fn line_padding(mut self, padding: f64) -> Self {
         self.line_padding = Some(padding);
         self
     }
 
+    /// Sets the distance between baselines as a multiple of the font size,
+    /// overriding `line_padding`
+    pub fn line_height(mut self, height: f64) -> Self {
+        self.line_height = Some(height);
+        self
+    }
+
     pub fn origin(mut self, origin: Point) -> Self {
         self.origin = Some(origin);
         self
     }
 
+    /// Draws the text with the bundled single-line font, stroking each
+    /// glyph's centerline once rather than tracing its outline, unless
+    /// another font is set with `font`. SVG fonts are always drawn this way.
+    pub fn single_line(mut self) -> Self {
+        self.single_line = true;
+        self
+    }
+
+    /// Sets how lines are aligned. Lines are aligned within `max_width` from
+    /// the origin if it is set, and otherwise around the origin, with
+    /// justified lines filling the width of the longest line.
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Sets which height of the first line is placed at the origin
+    pub fn anchor(mut self, anchor: TextAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Wraps lines at spaces so that none are wider than `width`, other than
+    /// those with single words that don't fit
+    pub fn max_width(mut self, width: f64) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// Adds space between each pair of letters, as a fraction of the font
+    /// size. Negative tracking brings letters closer together.
+    pub fn tracking(mut self, tracking: f64) -> Self {
+        self.tracking = tracking;
+        self
+    }
+
+    /// Sets whether the font's kerning pairs are applied, which they are by
+    /// default
+    pub fn kerning(mut self, kerning: bool) -> Self {
+        self.kerning = kerning;
+        self
+    }
+
+    /// Adjusts the space between `first` and a following `second` by a
+    /// fraction of the font size, in addition to any kerning from the font
+    pub fn kern_pair(mut self, first: char, second: char, adjustment: f64) -> Self {
+        self.kern_pairs.push((first, second, adjustment));
+        self
+    }
+
+    /// Lays the text out along a path rather than a straight line, rotating
+    /// each glyph to follow it. The origin is ignored, and each line after
+    /// the first is offset to the right of the path's direction.
+    pub fn along_path(mut self, path: &'a Path) -> Self {
+        self.along = Some(path);
+        self
+    }
+
+    /// Returns the bounds of the text before it's drawn, from the ascent of
+    /// the first line to the descent of the last and across the advances of
+    /// each line's glyphs. For text laid out along a path, x is the distance
+    /// along it and y the offset from it.
+    pub fn measure(&self) -> GeomResult<Rect> {
+        let font = self.resolve_font()?;
+        let lines = self.layout(&font);
+        let metrics = self.metrics(&font);
+        let x0 = lines.iter().map(|l| l.x).fold(f64::INFINITY, f64::min);
+        let x1 = lines
+            .iter()
+            .map(|l| l.x + l.width)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let first = lines.first().map_or(0., |l| l.baseline);
+        let last = lines.last().map_or(0., |l| l.baseline);
+        Ok(Rect::new(
+            x0,
+            first - metrics.ascent,
+            x1,
+            last - metrics.descent,
+        ))
+    }
+
     pub fn build(self) -> GeomResult<Path> {
-        let origin = if let Some(p) = self.origin {
-            p
-        } else {
-            Point::new(0., 0.)
+        let font = self.resolve_font()?;
+        let combined_cmds: Vec<PathEl> = self
+            .placed_glyphs(&font)
+            .into_iter()
+            .flat_map(|(_, glyphs)| glyphs)
+            .flat_map(|(_, _, cmds)| cmds)
+            .collect();
+        self.path(&font, &combined_cmds)
+    }
+
+    /// Builds the text as lines of glyphs, each with its own path, rather
+    /// than as a single path, so that glyphs can be transformed, filled or
+    /// combined with other shapes independently
+    pub fn build_glyphs(self) -> GeomResult<Vec<TextLine>> {
+        let font = self.resolve_font()?;
+        self.placed_glyphs(&font)
+            .into_iter()
+            .map(|(baseline, glyphs)| {
+                let glyphs = glyphs
+                    .into_iter()
+                    .map(|(glyph, origin, cmds)| {
+                        // Whitespace has no outline to draw
+                        let path = if cmds.is_empty() {
+                            None
+                        } else {
+                            Some(self.path(&font, &cmds)?)
+                        };
+                        let bounds = match &path {
+                            Some(path) => path.bounding_box(),
+                            None => Rect::from_points(origin, origin),
+                        };
+                        Ok(Glyph {
+                            character: glyph.c,
+                            advance: glyph.advance,
+                            origin,
+                            bounds,
+                            path,
+                        })
+                    })
+                    .collect::<GeomResult<_>>()?;
+                Ok(TextLine { baseline, glyphs })
+            })
+            .collect()
+    }
+
+    /// Returns the baseline of each line, and each of its glyphs with their
+    /// origin and the commands drawing them in place
+    #[allow(clippy::type_complexity)]
+    fn placed_glyphs(&self, font: &Font) -> Vec<(f64, Vec<(LaidOutGlyph, Point, Vec<PathEl>)>)> {
+        let placement = self.along.map(|p| PathPlacement::new(p.inner()));
+        self.layout(font)
+            .into_iter()
+            .map(|line| {
+                let baseline = line.baseline;
+                let glyphs = line
+                    .glyphs
+                    .into_iter()
+                    .map(|glyph| {
+                        let transform = match &placement {
+                            Some(placement) => placement.transform(&glyph, baseline),
+                            None => Affine::translate((glyph.x, baseline)),
+                        };
+                        let cmds = font
+                            .outline(glyph.c, self.font_size())
+                            .into_iter()
+                            .map(|el| transform * el)
+                            .collect();
+                        (glyph, transform * Point::ZERO, cmds)
+                    })
+                    .collect();
+                (baseline, glyphs)
+            })
+            .collect()
+    }
+
+    fn path(&self, font: &Font, cmds: &[PathEl]) -> GeomResult<Path> {
+        let stroke_width = match self.stroke_width {
+            Some(w) => w,
+            None => DEFAULT_STROKE_WIDTH,
         };
-        let size = if let Some(s) = self.size { s } else { 100. } as f32;
+
+        let mut builder = PathBuilder::new();
+        builder.commands(cmds);
+        if !font.is_single_line() {
+            builder.precompute();
+        }
+        builder.stroke_width(stroke_width).build()
+    }
+
+    fn font_size(&self) -> f64 {
+        self.size.unwrap_or(100.)
+    }
+
+    fn resolve_font(&self) -> GeomResult<Font> {
+        match (self.font, self.single_line) {
+            (Some(name), _) => load_font(name),
+            (None, false) => load_font(DEFAULT_FONT),
+            (None, true) => load_font(DEFAULT_SINGLE_LINE_FONT),
+        }
+    }
+
+    fn metrics(&self, font: &Font) -> LineMetrics {
+        let size = self.font_size();
+        let (ascent, descent) = font.v_metrics(size);
+        let line_advance = match self.line_height {
+            Some(height) => height * size,
+            None => ascent + self.line_padding.unwrap_or(50.),
+        };
+        LineMetrics {
+            ascent,
+            descent,
+            line_advance,
+        }
+    }
+
+    /// Returns the advance of each glyph in a string, including the kerning
+    /// and tracking before the next glyph
+    fn advances(&self, font: &Font, text: &str) -> Vec<(char, f64)> {
+        let size = self.font_size();
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .iter()
+            .enumerate()
+            .map(|(n, &c)| {
+                let mut advance = font.advance(c, size);
+                if let Some(&next) = chars.get(n + 1) {
+                    advance += self.tracking * size;
+                    if self.kerning {
+                        advance += font.kerning(c, next, size);
+                    }
+                    advance += self
+                        .kern_pairs
+                        .iter()
+                        .filter(|(first, second, _)| *first == c && *second == next)
+                        .map(|(_, _, adjustment)| adjustment * size)
+                        .sum::<f64>();
+                }
+                (c, advance)
+            })
+            .collect()
+    }
+
+    fn width(&self, font: &Font, text: &str) -> f64 {
+        self.advances(font, text).iter().map(|(_, a)| a).sum()
+    }
+
+    /// Splits the text into the lines it is drawn as, each marked with
+    /// whether it ends a text line
+    fn lines(&self, font: &Font) -> Vec<(String, bool)> {
         let text_lines = if !self.text_lines.is_empty() {
-            self.text_lines
+            self.text_lines.clone()
         } else {
             vec!["Lorem Ipsum"]
         };
-        let line_padding = if let Some(padding) = self.line_padding {
-            padding
-        } else {
-            50.
+        let max_width = match self.max_width {
+            Some(width) => width,
+            None => return text_lines.iter().map(|l| (l.to_string(), true)).collect(),
         };
 
-        let font_data = if let Some(path) = self.font {
-            let f = File::open(path)?;
-            let mut buf = Vec::new();
-            let mut reader = BufReader::new(f);
+        let mut lines = vec![];
+        for text_line in text_lines {
+            let mut line = String::new();
+            for word in text_line.split_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                if !line.is_empty() && self.width(font, &candidate) > max_width {
+                    lines.push((line, false));
+                    line = word.to_string();
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push((line, true));
+        }
+        lines
+    }
 
-            reader.read_to_end(&mut buf)?;
-            buf
-        } else {
-            include_bytes!("../../../assets/fonts/Jost-500-Medium.otf").to_vec()
+    /// Positions each glyph of each line, with x coordinates along the
+    /// baseline from the origin, or the start of the path being followed
+    fn layout(&self, font: &Font) -> Vec<LaidOutLine> {
+        let origin = self.origin.unwrap_or(Point::ZERO);
+        let metrics = self.metrics(font);
+        let lines = self.lines(font);
+        let advances: Vec<Vec<(char, f64)>> = lines
+            .iter()
+            .map(|(line, _)| self.advances(font, line))
+            .collect();
+        let widths: Vec<f64> = advances
+            .iter()
+            .map(|a| a.iter().map(|(_, a)| a).sum())
+            .collect();
+
+        let (start, available) = match self.along {
+            Some(path) => (0., Some(MeasuredPath::new(path.inner()).length())),
+            None => (origin.x, self.max_width),
         };
-        let font = Font::try_from_vec(font_data).ok_or_else(|| GeomError::font_error(""))?;
-
-        let scale = Scale { x: size, y: size };
-        let v_metrics = font.v_metrics(scale);
-        let base_offset = rusttype::point(
-            origin.x as f32,
-            origin.y as f32 + (2. / 3.) * v_metrics.ascent,
-        );
-        let mut adj_offset = base_offset;
-
-        let mut combined_cmds = vec![];
-
-        for t in text_lines {
-            let mut cmds = vec![];
-            let glyphs = font.layout(t, scale, adj_offset);
-
-            for g in glyphs {
-                let pos = g.position();
-
-                let mut path_outliner = PathOutlineBuilder::new(point(pos.x, pos.y));
-                let unpositioned_glyph = g.unpositioned();
-                unpositioned_glyph.build_outline(&mut path_outliner);
-
-                // Whitespace produces a list of empty commands in path_outliner,
-                // which when passed to Path::from_commands(cmds) produces an
-                // error. In this case, we'll skip any erroneous paths, assuming
-                // that whitespace is the only thing that triggers this, however
-                // as this is used, if anything else is getting dropped, this
-                // approach can be revisited
-                if let Ok(path) = path_outliner.path() {
-                    for cmd in path.commands() {
-                        cmds.push(*cmd);
-                        combined_cmds.push(*cmd);
-                    }
+        let widest = widths.iter().cloned().fold(0., f64::max);
+        let first_baseline = match self.along {
+            Some(_) => 0.,
+            None => origin.y,
+        } + match self.anchor {
+            TextAnchor::Baseline => 0.,
+            TextAnchor::Ascender => metrics.ascent,
+            TextAnchor::AscentFraction(f) => f * metrics.ascent,
+        };
+
+        let mut laid_out = vec![];
+        for (n, ((advances, width), (_, last))) in
+            advances.into_iter().zip(widths).zip(&lines).enumerate()
+        {
+            let mut x = match (self.align, available) {
+                (TextAlign::Center, Some(available)) => start + (available - width) / 2.,
+                (TextAlign::Center, None) => start - width / 2.,
+                (TextAlign::Right, Some(available)) => start + available - width,
+                (TextAlign::Right, None) => start - width,
+                (TextAlign::Left, _) | (TextAlign::Justify, _) => start,
+            };
+            let spaces = advances.iter().filter(|(c, _)| *c == ' ').count();
+            let stretch = match self.align {
+                TextAlign::Justify if !last && spaces > 0 => {
+                    (available.unwrap_or(widest) - width) / spaces as f64
+                }
+                _ => 0.,
+            };
+
+            let line_start = x;
+            let mut glyphs = vec![];
+            for (c, advance) in advances {
+                glyphs.push(LaidOutGlyph { c, x, advance });
+                x += advance;
+                if c == ' ' {
+                    x += stretch;
                 }
             }
-            adj_offset = adj_offset
-                + Vector {
-                    x: 0.0,
-                    y: (v_metrics.ascent + line_padding as f32),
-                };
+            laid_out.push(LaidOutLine {
+                x: line_start,
+                width: x - line_start,
+                baseline: first_baseline + n as f64 * metrics.line_advance,
+                glyphs,
+            });
         }
+        laid_out
+    }
+}
 
-        let stroke_width = match self.stroke_width {
-            Some(w) => w,
-            None => DEFAULT_STROKE_WIDTH,
-        };
+/// A line of text built with `TextBuilder::build_glyphs`
+#[derive(Clone, Debug)]
+pub struct TextLine {
+    /// Height of the line's baseline, or its offset from the path being
+    /// followed
+    pub baseline: f64,
+    pub glyphs: Vec<Glyph>,
+}
 
-        PathBuilder::new()
-            .commands(&combined_cmds)
-            .precompute()
-            .stroke_width(stroke_width)
-            .build()
+impl TextLine {
+    /// Returns the characters of the line
+    pub fn text(&self) -> String {
+        self.glyphs.iter().map(|g| g.character).collect()
     }
 }
 
-struct PathOutlineBuilder {
-    cmds: Vec<PathEl>,
-    translation: Point,
+/// A single glyph of text built with `TextBuilder::build_glyphs`
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    pub character: char,
+    /// Distance to the origin of the next glyph, including kerning and
+    /// tracking but not justification
+    pub advance: f64,
+    /// Position of the glyph's origin on the baseline
+    pub origin: Point,
+    /// Bounds of the glyph's path, or an empty rect at its origin if it has
+    /// none
+    pub bounds: Rect,
+    /// The glyph as drawn in place, which is `None` for whitespace
+    pub path: Option<Path>,
+}
+
+/// Vertical metrics of a font at a size, in canvas units
+struct LineMetrics {
+    ascent: f64,
+    /// Negative for fonts that extend below the baseline
+    descent: f64,
+    /// Distance between the baselines of consecutive lines
+    line_advance: f64,
 }
 
-impl PathOutlineBuilder {
-    fn new(translation: Point) -> Self {
+struct LaidOutLine {
+    x: f64,
+    width: f64,
+    baseline: f64,
+    glyphs: Vec<LaidOutGlyph>,
+}
+
+struct LaidOutGlyph {
+    c: char,
+    /// Offset of the glyph's origin along the baseline
+    x: f64,
+    advance: f64,
+}
+
+/// The path that text is laid out along
+struct PathPlacement {
+    measured: MeasuredPath,
+}
+
+impl PathPlacement {
+    fn new(path: &kurbo::BezPath) -> Self {
         Self {
-            cmds: vec![],
-            translation,
+            measured: MeasuredPath::new(path),
         }
     }
 
-    fn path(&self) -> GeomResult<Path> {
-        Path::from_commands(&self.cmds)
+    /// Returns the point at a distance along the path, and the direction of
+    /// the path there. Distances beyond either end of the path continue in
+    /// the direction of that end.
+    fn point_at(&self, distance: f64) -> (Point, Vec2) {
+        let position = self.measured.position(distance);
+        let overshoot = distance - distance.max(0.).min(self.measured.length());
+        (
+            position.point + position.tangent * overshoot,
+            position.tangent,
+        )
     }
 
-    fn point(&self, x: f32, y: f32) -> Point {
-        point(x + self.translation.x as f32, y + self.translation.y as f32)
+    /// Returns the transform placing a glyph on the path, rotated to follow
+    /// it about the middle of its advance
+    fn transform(&self, glyph: &LaidOutGlyph, baseline: f64) -> Affine {
+        let middle = glyph.advance / 2.;
+        let (point, direction) = self.point_at(glyph.x + middle);
+        Affine::translate(point.to_vec2())
+            * Affine::rotate(direction.atan2())
+            * Affine::translate((-middle, baseline))
     }
 }
 
-impl OutlineBuilder for PathOutlineBuilder {
-    fn move_to(&mut self, x: f32, y: f32) {
-        self.cmds.push(PathEl::MoveTo(self.point(x, y)));
-    }
-    fn line_to(&mut self, x: f32, y: f32) {
-        self.cmds.push(PathEl::LineTo(self.point(x, y)));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Line, PathEl, Shaped};
+
+    #[test]
+    fn single_line_strokes() {
+        let path = TextBuilder::new()
+            .single_line()
+            .size(100.)
+            .origin(point(10, 10))
+            .text_line("Hi")
+            .text_line("o")
+            .build()
+            .unwrap();
+        // Centerlines are open strokes, three for H and two for i
+        let cmds = path.commands();
+        assert!(!cmds.contains(&PathEl::ClosePath));
+        let strokes = cmds
+            .iter()
+            .filter(|cmd| matches!(cmd, PathEl::MoveTo(_)))
+            .count();
+        assert_eq!(strokes, 6);
+
+        // The cap height of the bundled font is 0.6 of the font's height
+        let bounds = path.bounding_box();
+        assert!((bounds.x0 - 15.).abs() < 1e-9);
+        assert!((bounds.y0 - (10. + (2. / 3.) * 80. - 60.)).abs() < 1e-9);
     }
-    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-        self.cmds
-            .push(PathEl::QuadTo(self.point(x1, y1), self.point(x, y)));
+
+    #[test]
+    fn alignment_and_wrapping() {
+        // The bundled font's 'l' is 12 units wide at size 100
+        let builder = || {
+            TextBuilder::new()
+                .single_line()
+                .size(100.)
+                .anchor(TextAnchor::Baseline)
+                .line_height(1.)
+        };
+        let bounds = builder().text_line("ll").measure().unwrap();
+        assert!((bounds.width() - 24.).abs() < 1e-9);
+        assert!((bounds.y0 + 80.).abs() < 1e-9 && (bounds.y1 - 20.).abs() < 1e-9);
+
+        let right = builder()
+            .text_line("ll")
+            .text_line("l")
+            .align(TextAlign::Right)
+            .origin(point(100, 0))
+            .measure()
+            .unwrap();
+        assert!((right.x1 - 100.).abs() < 1e-9 && (right.x0 - 76.).abs() < 1e-9);
+
+        let tracked = builder()
+            .text_line("lll")
+            .tracking(0.1)
+            .kern_pair('l', 'l', 0.05)
+            .measure()
+            .unwrap();
+        assert!((tracked.width() - (36. + 2. * 15.)).abs() < 1e-9);
+
+        // Words wrap to fit, and justified lines stretch to the full width
+        let wrapped = builder()
+            .text_line("l l l")
+            .max_width(60.)
+            .align(TextAlign::Justify);
+        let lines = wrapped.layout(&wrapped.resolve_font().unwrap());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].glyphs.len(), 3);
+        assert!((lines[0].width - 60.).abs() < 1e-9);
+        assert!((lines[1].width - 12.).abs() < 1e-9);
+        assert!((lines[1].baseline - 100.).abs() < 1e-9);
     }
-    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-        self.cmds.push(PathEl::CurveTo(
-            self.point(x1, y1),
-            self.point(x2, y2),
-            self.point(x, y),
-        ));
+
+    #[test]
+    fn along_path() {
+        // Text along a vertical line is rotated a quarter turn
+        let path = Line::new(point(0, 0), point(0, 1000)).unwrap().to_path();
+        let text = TextBuilder::new()
+            .single_line()
+            .size(100.)
+            .anchor(TextAnchor::Baseline)
+            .text_line("l")
+            .along_path(&path)
+            .build()
+            .unwrap();
+        let bounds = text.bounding_box();
+        assert!(bounds.x0.abs() < 1e-9 && (bounds.x1 - 60.).abs() < 1e-9);
+        assert!((bounds.y0 - 6.).abs() < 1e-9 && (bounds.y1 - 6.).abs() < 1e-9);
     }
-    fn close(&mut self) {
-        self.cmds.push(PathEl::ClosePath);
+
+    #[test]
+    fn glyphs() {
+        let text = TextBuilder::new()
+            .single_line()
+            .size(100.)
+            .anchor(TextAnchor::Baseline)
+            .text_line("Hi there")
+            .text_line("you");
+        let combined = text.clone().build().unwrap();
+        let lines = text.build_glyphs().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text(), "Hi there");
+        assert_eq!(lines[1].baseline, 130.);
+
+        let space = &lines[0].glyphs[2];
+        assert!(space.path.is_none());
+        assert_eq!(space.bounds.area(), 0.);
+        let glyph = &lines[0].glyphs[1];
+        assert_eq!(glyph.origin, point(lines[0].glyphs[0].advance, 0));
+        let path = glyph.path.as_ref().unwrap();
+        assert_eq!(glyph.bounds, path.bounding_box());
+        assert!(glyph.bounds.x0 >= glyph.origin.x && glyph.bounds.y1 <= 0.);
+
+        // Together the glyphs draw the same text as building it as one path
+        let commands: Vec<PathEl> = lines
+            .iter()
+            .flat_map(|l| &l.glyphs)
+            .filter_map(|g| g.path.as_ref())
+            .flat_map(|p| p.commands().to_vec())
+            .collect();
+        assert_eq!(commands, combined.commands());
     }
 }
@@ -0,0 +1,2 @@
+mod svg;
+pub use self::svg::{import_svg, import_svg_file};
@@ -0,0 +1,730 @@
+use crate::canvas::Canvas;
+use crate::geometry::{
+    Affine, Circle, Ellipse, GeomError, GeomResult, Line, Path, Point, Poly, Shape,
+    DEFAULT_TOLERANCE,
+};
+use crate::pen::{Color, Pen};
+use crate::units::{Size, Unit, PT};
+use kurbo::{BezPath, RoundedRect, Shape as KurboShape};
+use std::collections::HashMap;
+use svg::node::element::tag::Type;
+use svg::parser::Event;
+
+/// Elements whose contents are never drawn directly, and are skipped
+const SKIPPED: &[&str] = &[
+    "defs",
+    "title",
+    "desc",
+    "metadata",
+    "style",
+    "clipPath",
+    "mask",
+    "marker",
+    "pattern",
+    "symbol",
+    "linearGradient",
+    "radialGradient",
+];
+
+/// Styling inherited from enclosing groups
+#[derive(Clone, Default)]
+struct Style {
+    stroke_width: Option<f64>,
+    stroke: Option<Color>,
+    layer: Option<String>,
+}
+
+/// A canvas being filled from an open group
+struct Frame {
+    canvas: Canvas,
+    style: Style,
+    /// The factor by which the transforms from this canvas up to the page
+    /// scale lengths
+    stroke_scale: f64,
+}
+
+/// Reads an SVG document into a canvas of the document's size.
+///
+/// Groups become sub-canvases carrying their transforms, and Inkscape layers
+/// become pens named after the layer. Sub-canvas transforms don't scale
+/// stroke widths, so each stroke width is instead scaled by every transform
+/// above it, including the view box, to match how SVG viewers draw it.
+/// Elements that can't be represented as shapes, such as text or images, are
+/// reported as errors.
+pub fn import_svg(content: &str) -> GeomResult<Canvas> {
+    let mut frames: Vec<Frame> = vec![];
+    let mut skip_depth = 0;
+    for event in svg::read(content)? {
+        let (name, kind, attributes) = match event {
+            Event::Tag(name, kind, attributes) => (name, kind, attributes),
+            Event::Error(e) => return Err(GeomError::svg_error(&e.to_string())),
+            _ => continue,
+        };
+        let attributes: HashMap<String, String> = attributes
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        if skip_depth > 0 || SKIPPED.contains(&name) || name.contains(':') {
+            match kind {
+                Type::Start => skip_depth += 1,
+                Type::End => skip_depth -= 1,
+                Type::Empty => {}
+            }
+            continue;
+        }
+
+        match (name, kind) {
+            ("svg", Type::Start) | ("svg", Type::Empty) if frames.is_empty() => {
+                frames.extend(root_frames(&attributes)?)
+            }
+            ("svg", Type::End) => {}
+            (_, _) if frames.is_empty() => {
+                return Err(GeomError::svg_error("document has no root <svg> element"))
+            }
+            ("g", Type::Start) => {
+                let parent = frames.last().unwrap();
+                let mut canvas = Canvas::new(
+                    Point::ZERO,
+                    Size::new(parent.canvas.width(), parent.canvas.height()),
+                );
+                let transform = transform(&attributes)?;
+                canvas.set_transform(transform);
+                let mut style = inherit(&parent.style, &attributes)?;
+                if attributes.get("inkscape:groupmode").map(|m| m.as_str()) == Some("layer") {
+                    style.layer = attributes.get("inkscape:label").cloned();
+                }
+                frames.push(Frame {
+                    canvas,
+                    style,
+                    stroke_scale: parent.stroke_scale * transform.determinant().abs().sqrt(),
+                });
+            }
+            ("g", Type::End) => {
+                if frames.len() < 2 {
+                    return Err(GeomError::svg_error("unmatched </g>"));
+                }
+                let group = frames.pop().unwrap();
+                frames.last_mut().unwrap().canvas.add(group.canvas);
+            }
+            ("g", Type::Empty) => {}
+            (_, Type::End) => {}
+            (name, _) => {
+                let frame = frames.last_mut().unwrap();
+                let style = inherit(&frame.style, &attributes)?;
+                let mut shape = shape(name, &attributes)?;
+                if let Some(width) = style.stroke_width {
+                    shape.set_stroke_width(width);
+                }
+                let mut shape = shape.transform_with_stroke(transform(&attributes)?);
+                shape.set_stroke_width(shape.stroke() * frame.stroke_scale);
+                let pen = Pen::new(
+                    style.layer.as_deref().unwrap_or("default"),
+                    style.stroke.unwrap_or(Color::BLACK),
+                );
+                frame.canvas.add_with_pen(pen, shape);
+            }
+        }
+    }
+
+    while frames.len() > 1 {
+        let group = frames.pop().unwrap();
+        frames.last_mut().unwrap().canvas.add(group.canvas);
+    }
+    frames
+        .pop()
+        .map(|f| f.canvas)
+        .ok_or_else(|| GeomError::svg_error("document has no root <svg> element"))
+}
+
+/// Reads the SVG file at `path` into a canvas, as with `import_svg`
+pub fn import_svg_file(path: &str) -> GeomResult<Canvas> {
+    import_svg(&std::fs::read_to_string(path)?)
+}
+
+/// Creates the canvas for the root `<svg>` element, along with a sub-canvas
+/// mapping its view box onto the page if it has one
+fn root_frames(attributes: &HashMap<String, String>) -> GeomResult<Vec<Frame>> {
+    let view_box = match attributes.get("viewBox") {
+        Some(v) => match numbers(v)?.as_slice() {
+            &[x, y, w, h] if w > 0. && h > 0. => Some((x, y, w, h)),
+            _ => return Err(GeomError::svg_error(&format!("malformed viewBox '{}'", v))),
+        },
+        None => None,
+    };
+    let dimension = |name: &str, fallback: Option<f64>| match attributes.get(name) {
+        Some(v) if !v.ends_with('%') => length(v),
+        _ => fallback
+            .ok_or_else(|| GeomError::svg_error(&format!("document has no {} or viewBox", name))),
+    };
+    let mut width = dimension("width", view_box.map(|v| v.2))?;
+    let mut height = dimension("height", view_box.map(|v| v.3))?;
+    if let Some((_, _, w, h)) = view_box {
+        // Physical sizes are often written with limited precision, which
+        // shouldn't leave an otherwise one to one view box slightly scaled
+        if (width / w - 1.).abs() < 1e-5 && (height / h - 1.).abs() < 1e-5 {
+            width = w;
+            height = h;
+        }
+    }
+
+    let mut root = Canvas::new(Point::ZERO, Size::new(width, height));
+    let unit = attributes.get("width").and_then(|w| {
+        let suffix = w.trim_start_matches(|c: char| c.is_ascii_digit() || ".-+eE".contains(c));
+        suffix.parse::<Unit>().ok()
+    });
+    root.set_unit(unit.unwrap_or(Unit::Px));
+
+    let mut frames = vec![Frame {
+        canvas: root,
+        style: Style::default(),
+        stroke_scale: 1.,
+    }];
+    if let Some((x, y, w, h)) = view_box {
+        let transform =
+            Affine::scale_non_uniform(width / w, height / h) * Affine::translate((-x, -y));
+        if transform != Affine::default() {
+            let mut canvas = Canvas::new(Point::ZERO, Size::new(w, h));
+            canvas.set_transform(transform);
+            frames.push(Frame {
+                canvas,
+                style: Style::default(),
+                stroke_scale: transform.determinant().abs().sqrt(),
+            });
+        }
+    }
+    Ok(frames)
+}
+
+/// Returns the style of an element, given its parent's
+fn inherit(parent: &Style, attributes: &HashMap<String, String>) -> GeomResult<Style> {
+    let mut style = parent.clone();
+    // Properties in `style` take precedence over presentation attributes
+    let mut properties: Vec<(String, String)> = attributes
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    if let Some(css) = attributes.get("style") {
+        properties.extend(css.split(';').filter_map(|declaration| {
+            let mut parts = declaration.splitn(2, ':');
+            Some((
+                parts.next()?.trim().to_string(),
+                parts.next()?.trim().to_string(),
+            ))
+        }));
+    }
+    for (name, value) in properties {
+        match name.as_str() {
+            "stroke-width" => style.stroke_width = Some(length(&value)?),
+            "stroke" => style.stroke = color(&value)?.or(style.stroke),
+            _ => {}
+        }
+    }
+    Ok(style)
+}
+
+/// Builds the shape an element draws, in its own coordinates
+fn shape(name: &str, attributes: &HashMap<String, String>) -> GeomResult<Shape> {
+    let attr = |attribute: &str| match attributes.get(attribute) {
+        Some(v) => length(v),
+        None => Ok(0.),
+    };
+    let shape = match name {
+        "path" => {
+            let d = attributes.get("d").map(|d| d.as_str()).unwrap_or("");
+            let path = BezPath::from_svg(d)
+                .map_err(|e| GeomError::svg_error(&format!("malformed path data: {}", e)))?;
+            Shape::new(Path::from(path))
+        }
+        "circle" => Shape::new(Circle::new(
+            Point::new(attr("cx")?, attr("cy")?),
+            attr("r")?,
+        )),
+        "ellipse" => Shape::new(Ellipse::new(
+            Point::new(attr("cx")?, attr("cy")?),
+            (attr("rx")?, attr("ry")?),
+            0.,
+        )),
+        "line" => Shape::new(Line::new(
+            Point::new(attr("x1")?, attr("y1")?),
+            Point::new(attr("x2")?, attr("y2")?),
+        )?),
+        "polyline" | "polygon" => {
+            let points: Vec<Point> = numbers(attributes.get("points").map_or("", |p| p))?
+                .chunks_exact(2)
+                .map(|p| Point::new(p[0], p[1]))
+                .collect();
+            match name {
+                "polygon" => Shape::new(Poly::new(&points)?),
+                _ if points.len() < 2 => {
+                    return Err(GeomError::svg_error("polyline needs at least 2 points"))
+                }
+                _ => Shape::new(Path::from_points(&points)),
+            }
+        }
+        "rect" => {
+            let (x, y) = (attr("x")?, attr("y")?);
+            let (w, h) = (attr("width")?, attr("height")?);
+            let radius = match (attributes.get("rx"), attributes.get("ry")) {
+                (Some(r), _) | (None, Some(r)) => length(r)?,
+                (None, None) => 0.,
+            };
+            if radius > 0. {
+                let rect = RoundedRect::new(x, y, x + w, y + h, radius);
+                Shape::new(Path::from(rect.to_path(DEFAULT_TOLERANCE)))
+            } else {
+                Shape::new(Poly::new(&[
+                    Point::new(x, y),
+                    Point::new(x + w, y),
+                    Point::new(x + w, y + h),
+                    Point::new(x, y + h),
+                ])?)
+            }
+        }
+        _ => {
+            return Err(GeomError::svg_error(&format!(
+                "unsupported element <{}>",
+                name
+            )))
+        }
+    };
+    Ok(shape)
+}
+
+/// Parses a length, converting physical units to canvas units
+fn length(value: &str) -> GeomResult<f64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "" => 1.,
+        "pt" => PT,
+        unit => unit
+            .parse::<Unit>()
+            .map_err(|e| GeomError::svg_error(&e))?
+            .scale(),
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .map(|n| n * scale)
+        .map_err(|_| GeomError::svg_error(&format!("malformed length '{}'", value)))
+}
+
+/// Parses a list of numbers separated by whitespace and/or commas
+fn numbers(value: &str) -> GeomResult<Vec<f64>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|n| !n.is_empty())
+        .map(|n| {
+            n.parse()
+                .map_err(|_| GeomError::svg_error(&format!("malformed number '{}'", n)))
+        })
+        .collect()
+}
+
+/// Parses an element's `transform` attribute
+fn transform(attributes: &HashMap<String, String>) -> GeomResult<Affine> {
+    let value = match attributes.get("transform") {
+        Some(v) => v,
+        None => return Ok(Affine::default()),
+    };
+    let malformed = || GeomError::svg_error(&format!("malformed transform '{}'", value));
+    let mut transform = Affine::default();
+    let mut rest = value.as_str();
+    while !rest.trim().is_empty() {
+        let open = rest.find('(').ok_or_else(malformed)?;
+        let close = rest.find(')').ok_or_else(malformed)?;
+        let name = rest[..open].trim().trim_start_matches(',').trim();
+        let args = numbers(&rest[open + 1..close])?;
+        transform *= match (name, args.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine::new([a, b, c, d, e, f]),
+            ("translate", &[x]) => Affine::translate((x, 0.)),
+            ("translate", &[x, y]) => Affine::translate((x, y)),
+            ("scale", &[s]) => Affine::scale(s),
+            ("scale", &[x, y]) => Affine::scale_non_uniform(x, y),
+            ("rotate", &[a]) => Affine::rotate(a.to_radians()),
+            ("rotate", &[a, x, y]) => {
+                Affine::translate((x, y))
+                    * Affine::rotate(a.to_radians())
+                    * Affine::translate((-x, -y))
+            }
+            ("skewX", &[a]) => Affine::new([1., 0., a.to_radians().tan(), 1., 0., 0.]),
+            ("skewY", &[a]) => Affine::new([1., a.to_radians().tan(), 0., 1., 0., 0.]),
+            _ => return Err(malformed()),
+        };
+        rest = &rest[close + 1..];
+    }
+    Ok(transform)
+}
+
+/// Parses a stroke color. Paints that aren't a single color, like `none` or
+/// gradients, return `None` so that the inherited color is kept.
+fn color(value: &str) -> GeomResult<Option<Color>> {
+    let value = value.trim();
+    let unsupported = || GeomError::svg_error(&format!("unsupported color '{}'", value));
+    let lower = value.to_ascii_lowercase();
+    if ["none", "inherit", "currentcolor", "transparent"].contains(&lower.as_str())
+        || lower.starts_with("url(")
+    {
+        return Ok(None);
+    }
+
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(unsupported)?;
+        return match *digits.as_slice() {
+            [r, g, b] => Ok(Some(Color::rgb(r * 17, g * 17, b * 17))),
+            [r1, r2, g1, g2, b1, b2] => {
+                Ok(Some(Color::rgb(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)))
+            }
+            _ => Err(unsupported()),
+        };
+    }
+
+    if let Some(args) = lower
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        // Components are either all integers or all percentages
+        let components: Vec<u8> = args
+            .split(',')
+            .map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(percent) => percent.trim().parse::<f64>().map(|p| p * 2.55),
+                    None => c.parse::<f64>(),
+                }
+                .map(|c| c.round().clamp(0., 255.) as u8)
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|_| unsupported())?;
+        return match *components.as_slice() {
+            [r, g, b] => Ok(Some(Color::rgb(r, g, b))),
+            _ => Err(unsupported()),
+        };
+    }
+
+    let rgb = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, rgb)| *rgb)
+        .ok_or_else(unsupported)?;
+    Ok(Some(Color::rgb(
+        (rgb >> 16) as u8,
+        (rgb >> 8) as u8,
+        rgb as u8,
+    )))
+}
+
+/// The color keywords recognized by SVG
+const NAMED_COLORS: [(&str, u32); 147] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("grey", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::CanvasElement;
+    use crate::geometry::{point, Shaped};
+    use crate::render::SvgRenderer;
+    use crate::units::MM;
+
+    const DOCUMENT: &str = r##"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="50mm" viewBox="0 0 200 100">
+  <title>test</title>
+  <defs><circle cx="0" cy="0" r="1"/></defs>
+  <path d="M 10 10 L 20 10 Q 30 20 40 10 A 5 5 0 0 1 50 10 Z" stroke-width="2"/>
+  <circle cx="50" cy="50" r="10"/>
+  <ellipse cx="50" cy="50" rx="10" ry="5" transform="rotate(90 50 50)"/>
+  <g transform="translate(100, 0) scale(2)" style="stroke:#ff0000;stroke-width:0.5">
+    <line x1="0" y1="0" x2="10" y2="10"/>
+    <polyline points="0,0 10,0 10,10"/>
+    <polygon points="0 0 10 0 10 10"/>
+    <rect x="1" y="1" width="5" height="5"/>
+  </g>
+</svg>"##;
+
+    #[test]
+    fn imports_elements() {
+        let canvas = import_svg(DOCUMENT).unwrap();
+        assert!((canvas.width() - 100. * MM).abs() < 1e-9);
+        assert_eq!(canvas.unit(), Unit::Mm);
+
+        // Everything is inside a sub-canvas mapping the view box to the page
+        let view = match &canvas.elements()[..] {
+            [CanvasElement::Canvas(c)] => c,
+            _ => panic!("expected a single view box canvas"),
+        };
+        let elements = view.elements();
+        assert_eq!(elements.len(), 4);
+        // Stroke widths are scaled by the view box, which maps 200 units onto
+        // 100mm, as sub-canvas transforms leave them alone
+        let scale = 100. * MM / 200.;
+        let near = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(matches!(
+            &elements[0],
+            CanvasElement::Shape(Shape::Path(p), _) if near(p.stroke_width, 2. * scale)
+        ));
+        assert!(matches!(
+            &elements[1],
+            CanvasElement::Shape(Shape::Circle(_), _)
+        ));
+        // Rotating the ellipse a quarter turn about its center swaps its radii
+        match &elements[2] {
+            CanvasElement::Shape(Shape::Ellipse(e), _) => {
+                let radii = e.inner().radii();
+                assert!((radii.x - 10.).abs() < 1e-9 && (radii.y - 5.).abs() < 1e-9);
+                let bounds = e.to_path().inner().bounding_box();
+                assert!((bounds.height() - 20.).abs() < 1e-6);
+            }
+            _ => panic!("expected an ellipse"),
+        }
+
+        let group = match &elements[3] {
+            CanvasElement::Canvas(c) => c,
+            _ => panic!("expected a group"),
+        };
+        assert_eq!(group.transform(), Affine::new([2., 0., 0., 2., 100., 0.]));
+        let kinds: Vec<&str> = group
+            .elements()
+            .iter()
+            .map(|e| match e {
+                CanvasElement::Shape(Shape::Line(_), pen) if pen.color == Color::RED => "line",
+                CanvasElement::Shape(Shape::Path(_), _) => "path",
+                CanvasElement::Shape(Shape::Poly(p), _) if near(p.stroke(), scale) => "poly",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, ["line", "path", "poly", "poly"]);
+
+        // The group's line lands in page coordinates, scaled by the view box
+        let bounds = canvas.shapes()[3].to_path().inner().bounding_box();
+        assert!((bounds.x0 - 100. * scale).abs() < 1e-9);
+        assert!((bounds.x1 - 120. * scale).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scales_stroke_widths() {
+        let widths = |svg: &str| -> Vec<f64> {
+            let canvas = import_svg(svg).unwrap();
+            let layers = canvas.layers();
+            layers[0].1.iter().map(|s| s.stroke()).collect()
+        };
+        // Element and group transforms scale strokes alike, as the view box
+        // does
+        let element = r#"<svg width="10" height="10"><path d="M0 0 L1 1" transform="scale(2)" stroke-width="1"/></svg>"#;
+        let group = r#"<svg width="10" height="10"><g transform="scale(2)"><path d="M0 0 L1 1" stroke-width="1"/></g></svg>"#;
+        let view_box = r#"<svg width="10" height="10" viewBox="0 0 5 5"><path d="M0 0 L1 1" stroke-width="1"/></svg>"#;
+        assert_eq!(widths(element), [2.]);
+        assert_eq!(widths(group), [2.]);
+        assert_eq!(widths(view_box), [2.]);
+    }
+
+    #[test]
+    fn reports_unsupported() {
+        let text = r#"<svg width="10" height="10"><text x="0" y="0">hi</text></svg>"#;
+        assert!(matches!(import_svg(text), Err(GeomError::SvgError(m)) if m.contains("<text>")));
+
+        let transform = r#"<svg width="10" height="10"><g transform="spin(3)"></g></svg>"#;
+        assert!(import_svg(transform).is_err());
+        assert!(import_svg("<g></g>").is_err());
+
+        let paint = r#"<svg width="10" height="10"><g stroke="hsl(0, 0%, 0%)"></g></svg>"#;
+        assert!(matches!(import_svg(paint), Err(GeomError::SvgError(m)) if m.contains("hsl")));
+    }
+
+    #[test]
+    fn parses_colors() {
+        let red = Some(Color::RED);
+        assert_eq!(color("#f00").unwrap(), red);
+        assert_eq!(color("#FF0000").unwrap(), red);
+        assert_eq!(color("Red").unwrap(), red);
+        assert_eq!(color("rgb(255, 0, 0)").unwrap(), red);
+        assert_eq!(color("rgb(100%,0%,0%)").unwrap(), red);
+        assert_eq!(
+            color("cornflowerblue").unwrap(),
+            Some(Color::rgb(100, 149, 237))
+        );
+        assert_eq!(color("none").unwrap(), None);
+        assert_eq!(color("url(#gradient)").unwrap(), None);
+        assert!(color("#ff00").is_err());
+        assert!(color("rgb(1, 2)").is_err());
+        assert!(color("blurple").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add_pen(Pen::new("red", Color::RED));
+        canvas.add(Circle::new(point(50, 50), 10.));
+        let mut sub = canvas.uniform_margin_subcanvas(10.);
+        sub.add_to_layer("red", Line::new(point(0, 0), point(10, 10)).unwrap());
+        canvas.add(sub);
+
+        let imported = import_svg(&canvas.render_svg_string()).unwrap();
+        let (before, after) = (canvas.layers(), imported.layers());
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[1].0, Pen::new("red", Color::RED));
+        for ((_, a), (_, b)) in before.iter().zip(&after) {
+            for (a, b) in a.iter().zip(b) {
+                let (a, b) = (
+                    a.to_path().inner().bounding_box(),
+                    b.to_path().inner().bounding_box(),
+                );
+                assert!((a.x0 - b.x0).abs() < 1e-6 && (a.y1 - b.y1).abs() < 1e-6);
+            }
+        }
+    }
+}
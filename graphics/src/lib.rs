@@ -2,6 +2,9 @@
 
 pub mod canvas;
 pub mod geometry;
+pub mod import;
+pub mod optimize;
+pub mod pen;
 pub mod prelude;
 pub mod render;
 pub mod units;
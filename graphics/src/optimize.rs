@@ -0,0 +1,549 @@
+use crate::canvas::Canvas;
+use crate::geometry::{GridIndex, Path, PathEl, Point, Shape, DEFAULT_ACCURACY};
+use crate::units::Size;
+use kurbo::{BezPath, ParamCurve, PathSeg, Shape as KurboShape};
+use std::fmt;
+
+/// Settings for `optimize`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OptimizeSettings {
+    /// Consecutive strokes whose endpoints lie within this distance of each
+    /// other, in canvas units, are joined into a single stroke
+    pub merge_tolerance: f64,
+    /// The maximum number of 2-opt refinement passes made over the order of
+    /// strokes after the initial nearest-neighbour ordering
+    pub two_opt_passes: usize,
+}
+
+impl Default for OptimizeSettings {
+    fn default() -> Self {
+        Self {
+            merge_tolerance: 0.1,
+            two_opt_passes: 10,
+        }
+    }
+}
+
+/// Statistics describing the effect of `optimize`. Lengths are in canvas
+/// units, and pen-up travel is measured from the canvas's origin at the start
+/// of each layer.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct OptimizeStats {
+    pub strokes_before: usize,
+    pub strokes_after: usize,
+    /// Distance travelled while drawing, which optimization doesn't change
+    pub pen_down: f64,
+    pub pen_up_before: f64,
+    pub pen_up_after: f64,
+}
+
+impl fmt::Display for OptimizeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "strokes: {} -> {}, pen down: {:.1}, pen up: {:.1} -> {:.1}",
+            self.strokes_before,
+            self.strokes_after,
+            self.pen_down,
+            self.pen_up_before,
+            self.pen_up_after
+        )
+    }
+}
+
+/// A single continuous pen-down movement
+struct Stroke {
+    path: BezPath,
+    start: Point,
+    end: Point,
+    stroke_width: f64,
+}
+
+impl Stroke {
+    fn new(path: BezPath, stroke_width: f64) -> Option<Self> {
+        let start = path.segments().next()?.start();
+        let end = path.segments().last()?.end();
+        Some(Self {
+            path,
+            start,
+            end,
+            stroke_width,
+        })
+    }
+
+    fn closed(&self) -> bool {
+        matches!(self.path.elements().last(), Some(PathEl::ClosePath))
+    }
+
+    fn reversed(&self) -> Self {
+        let mut path = BezPath::new();
+        path.move_to(self.end);
+        for seg in self.path.segments().collect::<Vec<_>>().iter().rev() {
+            match seg {
+                PathSeg::Line(l) => path.line_to(l.p0),
+                PathSeg::Quad(q) => path.quad_to(q.p1, q.p0),
+                PathSeg::Cubic(c) => path.curve_to(c.p2, c.p1, c.p0),
+            }
+        }
+        if self.closed() {
+            path.close_path();
+        }
+        Self {
+            path,
+            start: self.end,
+            end: self.start,
+            stroke_width: self.stroke_width,
+        }
+    }
+
+    /// Extends the stroke with `other`, bridging any gap between them with a
+    /// straight line
+    fn append(&mut self, other: &Stroke) {
+        if self.closed() {
+            let mut elements = self.path.elements().to_vec();
+            elements.pop();
+            self.path = BezPath::from_vec(elements);
+            self.path.line_to(self.end);
+        }
+        if other.start != self.end {
+            self.path.line_to(other.start);
+        }
+        for el in other.path.elements().iter().skip(1) {
+            match el {
+                PathEl::ClosePath => self.path.line_to(other.end),
+                el => self.path.push(*el),
+            }
+        }
+        self.end = other.end;
+    }
+}
+
+/// Splits every shape into its individual subpaths
+fn strokes(shapes: &[Shape]) -> Vec<Stroke> {
+    let mut strokes = vec![];
+    for shape in shapes {
+        let mut current = BezPath::new();
+        for el in shape.to_path().inner().elements() {
+            if let PathEl::MoveTo(_) = el {
+                strokes.extend(Stroke::new(current, shape.stroke()));
+                current = BezPath::new();
+            }
+            current.push(*el);
+        }
+        strokes.extend(Stroke::new(current, shape.stroke()));
+    }
+    strokes
+}
+
+/// Returns the total pen-up distance when drawing `order`, given as indices
+/// into `strokes` and whether each is drawn in reverse
+fn travel(strokes: &[Stroke], order: &[(usize, bool)]) -> f64 {
+    let mut position = Point::ZERO;
+    let mut distance = 0.;
+    for &(i, reversed) in order {
+        let (start, end) = endpoints(&strokes[i], reversed);
+        distance += position.distance(start);
+        position = end;
+    }
+    distance
+}
+
+fn endpoints(stroke: &Stroke, reversed: bool) -> (Point, Point) {
+    if reversed {
+        (stroke.end, stroke.start)
+    } else {
+        (stroke.start, stroke.end)
+    }
+}
+
+/// Orders strokes by repeatedly drawing whichever remaining stroke has an
+/// endpoint nearest to the pen
+fn nearest_neighbour_order(strokes: &[Stroke]) -> Vec<(usize, bool)> {
+    let mut remaining: Vec<usize> = (0..strokes.len()).collect();
+    let mut order = Vec::with_capacity(strokes.len());
+    let mut position = Point::ZERO;
+    while !remaining.is_empty() {
+        let (n, reversed, _) = remaining
+            .iter()
+            .enumerate()
+            .flat_map(|(n, &i)| {
+                let s = &strokes[i];
+                [
+                    (n, false, position.distance(s.start)),
+                    (n, true, position.distance(s.end)),
+                ]
+            })
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+            .unwrap();
+        let i = remaining.swap_remove(n);
+        position = endpoints(&strokes[i], reversed).1;
+        order.push((i, reversed));
+    }
+    order
+}
+
+/// Repeatedly reverses runs of the order wherever doing so shortens travel,
+/// until no run can be improved or `passes` runs out
+fn two_opt(strokes: &[Stroke], order: &mut [(usize, bool)], passes: usize) {
+    let start = |o: (usize, bool)| endpoints(&strokes[o.0], o.1).0;
+    let end = |o: (usize, bool)| endpoints(&strokes[o.0], o.1).1;
+    for _ in 0..passes {
+        let mut improved = false;
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                let before_run = if i == 0 {
+                    Point::ZERO
+                } else {
+                    end(order[i - 1])
+                };
+                let after_run = order.get(j + 1).map(|o| start(*o));
+                let (run_start, run_end) = (start(order[i]), end(order[j]));
+
+                // Reversing the run swaps which of its ends meets each side
+                let current =
+                    before_run.distance(run_start) + after_run.map_or(0., |p| run_end.distance(p));
+                let reversed =
+                    before_run.distance(run_end) + after_run.map_or(0., |p| run_start.distance(p));
+                if reversed < current - 1e-9 {
+                    order[i..=j].reverse();
+                    for o in &mut order[i..=j] {
+                        o.1 = !o.1;
+                    }
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Reorders, reverses and joins the strokes of every shape in `canvas` to
+/// minimize the distance a plotter travels with its pen raised.
+///
+/// Each pen's layer is optimized separately, since it's plotted in a pass of
+/// its own. The result is a single flat canvas of paths in drawing order,
+/// layer by layer, with any sub-canvas transforms and clips already applied.
+pub fn optimize(canvas: &Canvas, settings: &OptimizeSettings) -> (Canvas, OptimizeStats) {
+    let mut optimized = Canvas::new(canvas.origin(), Size::new(canvas.width(), canvas.height()));
+    optimized.set_unit(canvas.unit());
+    let mut stats = OptimizeStats::default();
+    for (pen, shapes) in canvas.layers() {
+        let (strokes, layer_stats) = optimize_layer(&shapes, settings);
+        for stroke in strokes {
+            let mut path = Path::from(stroke.path);
+            path.stroke_width = stroke.stroke_width;
+            optimized.add_with_pen(pen.clone(), path);
+        }
+        stats.strokes_before += layer_stats.strokes_before;
+        stats.strokes_after += layer_stats.strokes_after;
+        stats.pen_down += layer_stats.pen_down;
+        stats.pen_up_before += layer_stats.pen_up_before;
+        stats.pen_up_after += layer_stats.pen_up_after;
+    }
+    (optimized, stats)
+}
+
+fn optimize_layer(shapes: &[Shape], settings: &OptimizeSettings) -> (Vec<Stroke>, OptimizeStats) {
+    let strokes = strokes(shapes);
+    let original_order: Vec<(usize, bool)> = (0..strokes.len()).map(|i| (i, false)).collect();
+    // Greedy ordering usually wins, but strokes generated in a sensible order
+    // can be better left as they are, so both are refined and compared
+    let order = vec![nearest_neighbour_order(&strokes), original_order.clone()]
+        .into_iter()
+        .map(|mut order| {
+            two_opt(&strokes, &mut order, settings.two_opt_passes);
+            order
+        })
+        .min_by(|a, b| {
+            travel(&strokes, a)
+                .partial_cmp(&travel(&strokes, b))
+                .unwrap()
+        })
+        .unwrap();
+
+    let mut merged: Vec<Stroke> = vec![];
+    for &(i, reversed) in &order {
+        let stroke = if reversed {
+            strokes[i].reversed()
+        } else {
+            Stroke::new(strokes[i].path.clone(), strokes[i].stroke_width).unwrap()
+        };
+        match merged.last_mut() {
+            Some(last)
+                if last.stroke_width == stroke.stroke_width
+                    && last.end.distance(stroke.start) <= settings.merge_tolerance =>
+            {
+                last.append(&stroke)
+            }
+            _ => merged.push(stroke),
+        }
+    }
+
+    let stats = OptimizeStats {
+        strokes_before: strokes.len(),
+        strokes_after: merged.len(),
+        pen_down: strokes
+            .iter()
+            .map(|s| s.path.perimeter(DEFAULT_ACCURACY))
+            .sum(),
+        pen_up_before: travel(&strokes, &original_order),
+        pen_up_after: travel(
+            &merged,
+            &(0..merged.len()).map(|i| (i, false)).collect::<Vec<_>>(),
+        ),
+    };
+
+    (merged, stats)
+}
+
+/// Statistics describing the effect of `dedupe`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DedupeStats {
+    /// The number of line segments the canvas was flattened into
+    pub segments: usize,
+    /// The total length of line that was found to be drawn more than once
+    /// and removed, in canvas units
+    pub removed_length: f64,
+}
+
+impl fmt::Display for DedupeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "segments: {}, removed length: {:.1}",
+            self.segments, self.removed_length
+        )
+    }
+}
+
+/// Returns the distance from `p` to the nearest point on the segment `a`→`b`
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len2 = ab.hypot2();
+    let t = if len2 == 0. {
+        0.
+    } else {
+        ((p - a).dot(ab) / len2).clamp(0., 1.)
+    };
+    p.distance(a + ab * t)
+}
+
+/// Returns the parameter range along `a`→`b` that the segment `p`→`q` runs
+/// along within `tolerance`, if any
+fn covered_range(a: Point, b: Point, p: Point, q: Point, tolerance: f64) -> Option<(f64, f64)> {
+    let ab = b - a;
+    let len2 = ab.hypot2();
+    if len2 == 0. {
+        return None;
+    }
+    let (tp, tq) = ((p - a).dot(ab) / len2, (q - a).dot(ab) / len2);
+    let (t0, t1) = (tp.min(tq).max(0.), tp.max(tq).min(1.));
+    if t1 <= t0 {
+        return None;
+    }
+    // The overlap is only shared if both of its ends are close to the other
+    // segment, which also rules out segments that merely cross
+    if segment_distance(a + ab * t0, p, q) <= tolerance
+        && segment_distance(a + ab * t1, p, q) <= tolerance
+    {
+        Some((t0, t1))
+    } else {
+        None
+    }
+}
+
+/// Removes every portion of line in `canvas` that retraces, within
+/// `tolerance`, a line drawn earlier in the canvas.
+///
+/// Shapes are compared after flattening into line segments. Those that
+/// overlap nothing drawn before them are kept as they are, while the rest are
+/// replaced by paths of their remaining segments. Lines drawn with different
+/// pens are never considered duplicates. The result is a single flat canvas
+/// with any sub-canvas transforms and clips already applied.
+pub fn dedupe(canvas: &Canvas, tolerance: f64) -> (Canvas, DedupeStats) {
+    let mut deduped = Canvas::new(canvas.origin(), Size::new(canvas.width(), canvas.height()));
+    deduped.set_unit(canvas.unit());
+    let mut stats = DedupeStats::default();
+    for (pen, shapes) in canvas.layers() {
+        let (shapes, layer_stats) = dedupe_layer(shapes, tolerance);
+        for shape in shapes {
+            deduped.add_with_pen(pen.clone(), shape);
+        }
+        stats.segments += layer_stats.segments;
+        stats.removed_length += layer_stats.removed_length;
+    }
+    (deduped, stats)
+}
+
+fn dedupe_layer(shapes: Vec<Shape>, tolerance: f64) -> (Vec<Shape>, DedupeStats) {
+    let mut segments = vec![];
+    let mut owners = vec![];
+    for (n, shape) in shapes.iter().enumerate() {
+        for line in shape.to_lines().unwrap_or_default() {
+            segments.push((line.p0(), line.p1()));
+            owners.push(n);
+        }
+    }
+
+    let bounds: Vec<(Point, Point)> = segments
+        .iter()
+        .map(|(a, b)| {
+            (
+                Point::new(a.x.min(b.x) - tolerance, a.y.min(b.y) - tolerance),
+                Point::new(a.x.max(b.x) + tolerance, a.y.max(b.y) + tolerance),
+            )
+        })
+        .collect();
+    let grid = GridIndex::new(&bounds);
+    let mut covered: Vec<Vec<(f64, f64)>> = vec![vec![]; segments.len()];
+    for (i, j) in grid.candidate_pairs(&bounds) {
+        // Only the later segment of each pair is redundant
+        let ((p, q), (a, b)) = (segments[i], segments[j]);
+        covered[j].extend(covered_range(a, b, p, q, tolerance));
+    }
+
+    // Split each segment into the pieces not covered by earlier segments
+    let mut removed_length = 0.;
+    let mut pieces: Vec<Vec<(Point, Point)>> = vec![vec![]; shapes.len()];
+    let mut changed = vec![false; shapes.len()];
+    for (n, (&(a, b), ranges)) in segments.iter().zip(covered.iter_mut()).enumerate() {
+        ranges.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+        let mut kept = vec![];
+        let mut t = 0.;
+        for &(t0, t1) in ranges.iter() {
+            if t0 > t {
+                kept.push((t, t0));
+            }
+            t = t.max(t1);
+        }
+        if t < 1. {
+            kept.push((t, 1.));
+        }
+
+        // Slivers left between overlaps are too short to be worth drawing
+        let length = a.distance(b);
+        if !ranges.is_empty() {
+            kept.retain(|(t0, t1)| (t1 - t0) * length > tolerance);
+            let kept_length: f64 = kept.iter().map(|(t0, t1)| (t1 - t0) * length).sum();
+            removed_length += length - kept_length;
+            changed[owners[n]] = true;
+        }
+        pieces[owners[n]].extend(kept.iter().map(|(t0, t1)| (a.lerp(b, *t0), a.lerp(b, *t1))));
+    }
+
+    let mut deduped = vec![];
+    for (n, shape) in shapes.into_iter().enumerate() {
+        if !changed[n] {
+            deduped.push(shape);
+            continue;
+        }
+        let mut path = BezPath::new();
+        let mut end = None;
+        for &(p, q) in &pieces[n] {
+            if end != Some(p) {
+                path.move_to(p);
+            }
+            path.line_to(q);
+            end = Some(q);
+        }
+        if !path.elements().is_empty() {
+            let mut path = Path::from(path);
+            path.stroke_width = shape.stroke();
+            deduped.push(Shape::new(path));
+        }
+    }
+
+    let stats = DedupeStats {
+        segments: segments.len(),
+        removed_length,
+    };
+    (deduped, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line, Shape, Shaped};
+
+    #[test]
+    fn reorders_and_reverses() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add(Line::new(point(50, 0), point(60, 0)).unwrap());
+        canvas.add(Line::new(point(10, 0), point(0, 0)).unwrap());
+        canvas.add(Line::new(point(20, 0), point(30, 0)).unwrap());
+        canvas.add(Line::new(point(49, 0), point(30, 0)).unwrap());
+
+        let (optimized, stats) = optimize(&canvas, &OptimizeSettings::default());
+        assert_eq!(stats.strokes_before, 4);
+        assert!((stats.pen_down - 49.).abs() < 1e-9);
+        assert!((stats.pen_up_before - (50. + 50. + 20. + 19.)).abs() < 1e-9);
+
+        // The last two lines meet end to end once the fourth is reversed
+        assert_eq!(stats.strokes_after, 3);
+        assert!((stats.pen_up_after - 11.).abs() < 1e-9);
+        assert_eq!(optimized.elements().len(), 3);
+    }
+
+    #[test]
+    fn closed_strokes_merge() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add(Circle::new(point(20, 10), 10.));
+        canvas.add(Line::new(point(0, 10), point(0, 0)).unwrap());
+        canvas.add(Line::new(point(30, 10), point(40, 10)).unwrap());
+
+        let (optimized, stats) = optimize(&canvas, &OptimizeSettings::default());
+        // The circle starts and ends where the second line starts
+        assert_eq!(stats.strokes_after, 2);
+        assert!((stats.pen_up_after - 30.).abs() < 1e-9);
+
+        let drawn: f64 = optimized
+            .shapes()
+            .iter()
+            .map(|s| s.to_path().perimeter())
+            .sum();
+        assert!((drawn - stats.pen_down).abs() < 1e-6);
+    }
+
+    #[test]
+    fn removes_overlaps() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add(Line::new(point(0, 0), point(50, 0)).unwrap());
+        canvas.add(Line::new(point(60, 0.05), point(20, 0.05)).unwrap());
+        canvas.add(Line::new(point(30, 0), point(40, 0)).unwrap());
+        canvas.add(Line::new(point(0, 1), point(50, 1)).unwrap());
+        canvas.add(Circle::new(point(50, 50), 10.));
+        canvas.add(Circle::new(point(50, 50), 10.));
+
+        let (deduped, stats) = dedupe(&canvas, 0.1);
+        let circumference = Circle::new(point(50, 50), 10.).to_path().perimeter();
+        assert!((stats.removed_length - (30. + 10. + circumference)).abs() < 0.1);
+
+        // Untouched shapes are kept as they were
+        let shapes = deduped.shapes();
+        assert_eq!(shapes.len(), 4);
+        assert!(matches!(shapes[0], Shape::Line(_)));
+        assert!(matches!(shapes[2], Shape::Line(_)));
+        assert!(matches!(shapes[3], Shape::Circle(_)));
+        let rest = shapes[1].to_path().bounding_box();
+        assert!((rest.x0 - 50.).abs() < 1e-6 && (rest.x1 - 60.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn layers_kept_apart() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add_to_layer("a", Line::new(point(0, 0), point(10, 0)).unwrap());
+        canvas.add_to_layer("b", Line::new(point(10, 0), point(20, 0)).unwrap());
+        canvas.add_to_layer("b", Line::new(point(0, 0), point(10, 0)).unwrap());
+
+        let (deduped, stats) = dedupe(&canvas, 0.1);
+        assert_eq!(stats.removed_length, 0.);
+        let (optimized, stats) = optimize(&deduped, &OptimizeSettings::default());
+        assert_eq!(stats.strokes_after, 2);
+        let layers = optimized.layers();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[1].0.name, "b");
+        assert!((layers[1].1[0].to_path().perimeter() - 20.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,48 @@
+/// An opaque RGB color
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Returns the color in the `#rrggbb` form used by SVG and CSS
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// A pen that shapes are plotted with. Shapes drawn with equal pens are
+/// grouped into a single layer when rendering, so that each layer can be
+/// plotted in one go between pen swaps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pen {
+    pub name: String,
+    pub color: Color,
+}
+
+impl Pen {
+    pub fn new(name: &str, color: Color) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+        }
+    }
+}
+
+impl Default for Pen {
+    fn default() -> Self {
+        Self::new("default", Color::BLACK)
+    }
+}
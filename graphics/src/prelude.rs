@@ -1 +1,3 @@
-pub use crate::{canvas::*, geometry::*, render::*, units::*, utils::*};
+pub use crate::import::{import_svg, import_svg_file};
+pub use crate::optimize::{dedupe, optimize, OptimizeSettings};
+pub use crate::{canvas::*, geometry::*, pen::*, render::*, units::*, utils::*};
@@ -2,8 +2,6 @@ use crate::canvas::*;
 use crate::geometry::{Circle, Point, Shape, Shaped};
 use egui::{Color32, Pos2, Shape as EguiShape, Stroke};
 
-const WHITE: Color32 = Color32::from_rgb(255, 255, 255);
-
 pub trait EguiRenderer {
     fn render_egui(&self) -> (egui::Vec2, Vec<EguiShape>);
 }
@@ -17,45 +15,39 @@ impl EguiRenderer for Canvas {
     }
 }
 
-pub trait EguiRenderable {
-    fn render(&self) -> Vec<EguiShape>;
-}
-
-impl EguiRenderable for CanvasElement {
+impl Canvas {
     fn render(&self) -> Vec<EguiShape> {
-        match self {
-            Self::Canvas(c) => c.render(),
-            Self::Shape(s) => s.render(),
-        }
-    }
-}
-
-impl EguiRenderable for Canvas {
-    fn render(&self) -> Vec<EguiShape> {
-        self.elements()
+        // egui can't transform or clip arbitrary shapes itself, so sub-canvas
+        // frames are applied to the geometry before it's drawn
+        self.layers()
             .iter()
-            .map(|e| e.render())
-            .flatten()
+            .flat_map(|(pen, shapes)| {
+                let color = Color32::from_rgb(pen.color.r, pen.color.g, pen.color.b);
+                shapes.iter().flat_map(move |s| s.render(color))
+            })
             .collect()
     }
 }
 
+trait EguiRenderable {
+    fn render(&self, color: Color32) -> Vec<EguiShape>;
+}
+
 impl EguiRenderable for Circle {
-    fn render(&self) -> Vec<EguiShape> {
+    fn render(&self, color: Color32) -> Vec<EguiShape> {
         let c = self.inner().center;
         vec![EguiShape::circle_stroke(
             Pos2::new(c.x as f32, c.y as f32),
             self.inner().radius as f32,
-            // TODO: allow stroke to be set at or before render time
-            Stroke::new(self.stroke() as f32, WHITE),
+            Stroke::new(self.stroke() as f32, color),
         )]
     }
 }
 
 impl EguiRenderable for Shape {
-    fn render(&self) -> Vec<EguiShape> {
+    fn render(&self, color: Color32) -> Vec<EguiShape> {
         match self {
-            Self::Circle(c) => c.render(),
+            Self::Circle(c) => c.render(color),
             _ => {
                 fn p(point: &Point) -> Pos2 {
                     Pos2::new(point.x as f32, point.y as f32)
@@ -67,7 +59,7 @@ impl EguiRenderable for Shape {
                         .iter()
                         .map(|line| EguiShape::LineSegment {
                             points: [p(&line.p0()), p(&line.p1())],
-                            stroke: Stroke::new(self.stroke() as f32, WHITE),
+                            stroke: Stroke::new(self.stroke() as f32, color),
                         })
                         .collect(),
                     _ => vec![],
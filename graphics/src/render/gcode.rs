@@ -0,0 +1,346 @@
+use crate::canvas::*;
+use crate::geometry::{Affine, Ellipse, Point, Shape, Vec2};
+use crate::render::{RenderError, RenderResult};
+use crate::units::{Size, MM};
+use std::f64::consts::TAU;
+use std::fmt::Write as FmtWrite;
+use std::io::Write;
+
+/// Number of arcs an ellipse is approximated with when writing arcs
+const ELLIPSE_ARCS: usize = 32;
+
+/// How the machine raises and lowers its pen
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PenLift {
+    /// Moves the Z axis to the given heights, in millimeters
+    Z { up: f64, down: f64 },
+    /// Sets a servo to the given positions with `M3 S..`, as used by GRBL
+    /// servo builds
+    Servo { up: u32, down: u32 },
+}
+
+/// Settings for `GcodeRenderer`. Coordinates are written in millimeters,
+/// with the y axis pointing up so that the top of the canvas is furthest
+/// from the machine's origin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GcodeSettings {
+    pub pen_lift: PenLift,
+    /// Feed rate while drawing, in millimeters per minute
+    pub feed_rate: f64,
+    /// Time to wait after lowering the pen before moving, in seconds
+    pub dwell: f64,
+    /// Position of the canvas's bottom left corner, in millimeters from the
+    /// machine's origin
+    pub origin: Point,
+    /// The machine's reachable area in millimeters, starting at its origin.
+    /// Rendering fails if any part of the drawing falls outside of it.
+    pub work_area: Option<Size>,
+    /// Writes circles and ellipses as `G2`/`G3` arcs rather than flattening
+    /// them into line segments
+    pub arcs: bool,
+}
+
+impl Default for GcodeSettings {
+    fn default() -> Self {
+        Self {
+            pen_lift: PenLift::Z { up: 5., down: 0. },
+            feed_rate: 1000.,
+            dwell: 0.,
+            origin: Point::ZERO,
+            work_area: None,
+            arcs: false,
+        }
+    }
+}
+
+pub trait GcodeRenderer {
+    /// Writes the canvas as G-code, pausing for a pen change between layers
+    fn render_gcode_to_writer(
+        &self,
+        writer: impl Write,
+        settings: &GcodeSettings,
+    ) -> RenderResult<()>;
+
+    /// Returns the canvas as G-code, pausing for a pen change between layers
+    fn render_gcode_string(&self, settings: &GcodeSettings) -> RenderResult<String>;
+
+    /// Saves the canvas as a G-code file, pausing for a pen change between
+    /// layers
+    fn render_gcode(&self, path: &str, settings: &GcodeSettings) -> RenderResult<()>;
+}
+
+impl GcodeRenderer for Canvas {
+    fn render_gcode_to_writer(
+        &self,
+        mut writer: impl Write,
+        settings: &GcodeSettings,
+    ) -> RenderResult<()> {
+        Ok(writer.write_all(self.render_gcode_string(settings)?.as_bytes())?)
+    }
+
+    fn render_gcode_string(&self, settings: &GcodeSettings) -> RenderResult<String> {
+        let mut gcode = Gcode {
+            out: String::new(),
+            settings,
+            to_machine: Affine::translate(settings.origin.to_vec2())
+                * Affine::new([1. / MM, 0., 0., -1. / MM, 0., self.height() / MM]),
+        };
+        gcode.line("G21");
+        gcode.line("G90");
+        gcode.pen_up();
+
+        let layers = self.layers();
+        for (pen, shapes) in &layers {
+            gcode.line(&format!("; pen: {}", pen.name));
+            if layers.len() > 1 {
+                gcode.line("M0");
+            }
+            for shape in shapes {
+                gcode.shape(shape)?;
+            }
+        }
+        gcode.line("G0 X0 Y0");
+        Ok(gcode.out)
+    }
+
+    fn render_gcode(&self, path: &str, settings: &GcodeSettings) -> RenderResult<()> {
+        self.render_gcode_to_writer(std::fs::File::create(path)?, settings)
+    }
+}
+
+/// G-code being written for a canvas
+struct Gcode<'a> {
+    out: String,
+    settings: &'a GcodeSettings,
+    to_machine: Affine,
+}
+
+impl<'a> Gcode<'a> {
+    fn line(&mut self, line: &str) {
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn pen_up(&mut self) {
+        match self.settings.pen_lift {
+            PenLift::Z { up, .. } => self.line(&format!("G0 Z{:.3}", up)),
+            PenLift::Servo { up, .. } => self.line(&format!("M3 S{}", up)),
+        }
+    }
+
+    fn pen_down(&mut self) {
+        match self.settings.pen_lift {
+            PenLift::Z { down, .. } => {
+                self.line(&format!("G1 Z{:.3} F{}", down, self.settings.feed_rate))
+            }
+            PenLift::Servo { down, .. } => self.line(&format!("M3 S{}", down)),
+        }
+        if self.settings.dwell > 0. {
+            self.line(&format!("G4 P{}", self.settings.dwell));
+        }
+    }
+
+    /// Checks that a point, in machine coordinates, is within the work area
+    fn check(&self, p: Point) -> RenderResult<()> {
+        match self.settings.work_area {
+            Some(area)
+                if p.x < -1e-6
+                    || p.y < -1e-6
+                    || p.x > area.width + 1e-6
+                    || p.y > area.height + 1e-6 =>
+            {
+                Err(RenderError::OutOfBounds(p))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Draws a stroke starting at `start`, in machine coordinates, that
+    /// `moves` writes the pen-down moves of
+    fn stroke(&mut self, start: Point, moves: &str) -> RenderResult<()> {
+        self.check(start)?;
+        self.line(&format!("G0 X{:.3} Y{:.3}", start.x, start.y));
+        self.pen_down();
+        self.out.push_str(moves);
+        self.pen_up();
+        Ok(())
+    }
+
+    fn shape(&mut self, shape: &Shape) -> RenderResult<()> {
+        match shape {
+            Shape::Circle(c) if self.settings.arcs => {
+                let center = self.to_machine * c.center();
+                let radius = c.radius() / MM;
+                for offset in &[(-radius, -radius), (radius, radius)] {
+                    self.check(center + Vec2::new(offset.0, offset.1))?;
+                }
+                let start = center + Vec2::new(radius, 0.);
+                let moves = format!(
+                    "G2 X{:.3} Y{:.3} I{:.3} J0 F{}\n",
+                    start.x, start.y, -radius, self.settings.feed_rate
+                );
+                self.stroke(start, &moves)
+            }
+            Shape::Ellipse(e) if self.settings.arcs => self.ellipse(e),
+            _ => {
+                for points in shape.inner().to_points() {
+                    let points: Vec<Point> = points.iter().map(|p| self.to_machine * *p).collect();
+                    if points.len() < 2 {
+                        continue;
+                    }
+                    let mut moves = String::new();
+                    for (n, p) in points.iter().enumerate().skip(1) {
+                        self.check(*p)?;
+                        write!(moves, "G1 X{:.3} Y{:.3}", p.x, p.y).unwrap();
+                        if n == 1 {
+                            write!(moves, " F{}", self.settings.feed_rate).unwrap();
+                        }
+                        moves.push('\n');
+                    }
+                    self.stroke(points[0], &moves)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Approximates an ellipse with circular arcs, each passing through
+    /// three points on the ellipse
+    fn ellipse(&mut self, ellipse: &Ellipse) -> RenderResult<()> {
+        let radii = ellipse.radii();
+        let to_machine = self.to_machine
+            * Affine::translate(ellipse.center().to_vec2())
+            * Affine::rotate(ellipse.inner().rotation());
+        let points: Vec<Point> = (0..=ELLIPSE_ARCS * 2)
+            .map(|n| {
+                let t = n as f64 / (ELLIPSE_ARCS * 2) as f64 * TAU;
+                to_machine * Point::new(radii.x * t.cos(), radii.y * t.sin())
+            })
+            .collect();
+
+        let mut moves = String::new();
+        for (n, arc) in points.windows(3).step_by(2).enumerate() {
+            let (a, b, c) = (arc[0], arc[1], arc[2]);
+            self.check(b)?;
+            self.check(c)?;
+            let command = match circle_center(a, b, c) {
+                Some(center) => {
+                    let offset = center - a;
+                    let clockwise = (b - a).cross(c - b) < 0.;
+                    format!(
+                        "{} X{:.3} Y{:.3} I{:.3} J{:.3}",
+                        if clockwise { "G2" } else { "G3" },
+                        c.x,
+                        c.y,
+                        offset.x,
+                        offset.y
+                    )
+                }
+                // Flat stretches of very eccentric ellipses are straight
+                None => format!("G1 X{:.3} Y{:.3}", c.x, c.y),
+            };
+            moves.push_str(&command);
+            if n == 0 {
+                write!(moves, " F{}", self.settings.feed_rate).unwrap();
+            }
+            moves.push('\n');
+        }
+        self.stroke(points[0], &moves)
+    }
+}
+
+/// Returns the center of the circle through three points, if they aren't
+/// collinear
+fn circle_center(a: Point, b: Point, c: Point) -> Option<Point> {
+    let (ab, ac) = (b - a, c - a);
+    let d = 2. * ab.cross(ac);
+    if d.abs() < 1e-9 {
+        return None;
+    }
+    let (ab2, ac2) = (ab.hypot2(), ac.hypot2());
+    let offset = Vec2::new(ac.y * ab2 - ab.y * ac2, ab.x * ac2 - ac.x * ab2) / d;
+    Some(a + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line};
+    use crate::pen::{Color, Pen};
+
+    #[test]
+    fn lines_and_pen_changes() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100. * MM, 50. * MM));
+        canvas.add(Line::new(point(0, 0), point(10. * MM, 0)).unwrap());
+        canvas.add_with_pen(
+            Pen::new("red", Color::RED),
+            Line::new(point(0, 0), point(0, 10. * MM)).unwrap(),
+        );
+        let settings = GcodeSettings {
+            pen_lift: PenLift::Servo { up: 30, down: 90 },
+            dwell: 0.2,
+            ..Default::default()
+        };
+        let gcode = canvas.render_gcode_string(&settings).unwrap();
+        let lines: Vec<&str> = gcode.lines().collect();
+        assert_eq!(
+            lines[..10],
+            [
+                "G21",
+                "G90",
+                "M3 S30",
+                "; pen: default",
+                "M0",
+                "G0 X0.000 Y50.000",
+                "M3 S90",
+                "G4 P0.2",
+                "G1 X10.000 Y50.000 F1000",
+                "M3 S30",
+            ]
+        );
+        assert!(gcode.contains("; pen: red\nM0\nG0 X0.000 Y50.000\n"));
+        assert!(gcode.contains("G1 X0.000 Y40.000 F1000\n"));
+
+        let cramped = GcodeSettings {
+            work_area: Some(Size::new(20., 20.)),
+            ..settings
+        };
+        assert!(matches!(
+            canvas.render_gcode_string(&cramped),
+            Err(RenderError::OutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn arcs() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100. * MM, 100. * MM));
+        canvas.add(Circle::new(point(50. * MM, 50. * MM), 10. * MM));
+        canvas.add(Ellipse::new(
+            point(50. * MM, 50. * MM),
+            (20. * MM, 10. * MM),
+            0.,
+        ));
+        let settings = GcodeSettings {
+            arcs: true,
+            ..Default::default()
+        };
+        let gcode = canvas.render_gcode_string(&settings).unwrap();
+        assert!(
+            gcode.contains("G0 X60.000 Y50.000\nG1 Z0.000 F1000\nG2 X60.000 Y50.000 I-10.000 J0")
+        );
+
+        // The ellipse's arcs run from and back to the end of its major axis
+        assert!(gcode.contains("G0 X70.000 Y50.000"));
+        let arcs: Vec<&str> = gcode.lines().filter(|l| l.starts_with("G2 ")).collect();
+        // Along with the circle's single arc
+        assert_eq!(arcs.len(), ELLIPSE_ARCS + 1);
+        assert!(arcs.last().unwrap().starts_with("G2 X70.000 Y50.000"));
+    }
+
+    #[test]
+    fn three_point_circle() {
+        let center = circle_center(point(1, 0), point(0, 1), point(-1, 0)).unwrap();
+        assert!(center.distance(Point::ZERO) < 1e-9);
+        assert!(circle_center(point(0, 0), point(1, 1), point(2, 2)).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::canvas::*;
+use crate::geometry::{Affine, Point};
+use crate::render::RenderResult;
+use crate::units::{PaperSize, Size, MM};
+use std::io::Write;
+
+/// HPGL plotter units per millimeter
+pub const PLOTTER_UNITS_PER_MM: f64 = 40.;
+
+/// Settings describing how a canvas is placed on the plotter's page
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HpglSettings {
+    /// Position of the page's lower left corner, in millimeters from the
+    /// plotter's origin
+    pub origin: Point,
+    /// Turns the canvas a quarter turn, so that its top edge runs along the
+    /// left edge of the page, e.g. to plot a portrait canvas on a plotter
+    /// that feeds paper in landscape
+    pub rotate: bool,
+    /// The paper being plotted on, as loaded into the plotter, which the
+    /// canvas is centered on. Defaults to the canvas's own size.
+    pub paper: Option<PaperSize>,
+    /// Pen velocity in centimeters per second, or the plotter's default if
+    /// not given
+    pub velocity: Option<f64>,
+}
+
+impl Default for HpglSettings {
+    fn default() -> Self {
+        Self {
+            origin: Point::ZERO,
+            rotate: false,
+            paper: None,
+            velocity: None,
+        }
+    }
+}
+
+pub trait HpglRenderer {
+    /// Writes the canvas as an HPGL command stream, selecting a new pen
+    /// for each layer
+    fn render_hpgl_to_writer(
+        &self,
+        writer: impl Write,
+        settings: &HpglSettings,
+    ) -> RenderResult<()>;
+
+    /// Returns the canvas as an HPGL command stream, selecting a new pen for
+    /// each layer
+    fn render_hpgl_string(&self, settings: &HpglSettings) -> String;
+
+    /// Saves the canvas as an HPGL file, selecting a new pen for each layer
+    fn render_hpgl(&self, path: &str, settings: &HpglSettings) -> RenderResult<()>;
+}
+
+impl HpglRenderer for Canvas {
+    fn render_hpgl_to_writer(
+        &self,
+        mut writer: impl Write,
+        settings: &HpglSettings,
+    ) -> RenderResult<()> {
+        Ok(writer.write_all(self.render_hpgl_string(settings).as_bytes())?)
+    }
+
+    fn render_hpgl_string(&self, settings: &HpglSettings) -> String {
+        let transform = self.plotter_transform(settings);
+        let mut commands = vec!["IN".to_string()];
+        for (n, (_, shapes)) in self.layers().iter().enumerate() {
+            // Pens are numbered by carousel slot, in the order layers are
+            // first used
+            commands.push(format!("SP{}", n + 1));
+            if let Some(velocity) = settings.velocity {
+                commands.push(format!("VS{}", velocity));
+            }
+            for points in shapes.iter().flat_map(|s| s.inner().to_points()) {
+                let mut points = points.iter().map(|p| {
+                    let p = transform * *p;
+                    format!("{},{}", p.x.round(), p.y.round())
+                });
+                if let Some(start) = points.next() {
+                    commands.push(format!("PU{}", start));
+                    commands.push(format!("PD{}", points.collect::<Vec<_>>().join(",")));
+                }
+            }
+        }
+        commands.push("PU".to_string());
+        commands.push("SP0".to_string());
+        commands.iter().map(|c| format!("{};\n", c)).collect()
+    }
+
+    fn render_hpgl(&self, path: &str, settings: &HpglSettings) -> RenderResult<()> {
+        self.render_hpgl_to_writer(std::fs::File::create(path)?, settings)
+    }
+}
+
+impl Canvas {
+    /// Returns the transform from canvas units to plotter units. The
+    /// plotter's y axis points up the page, so the canvas is flipped as well
+    /// as scaled.
+    fn plotter_transform(&self, settings: &HpglSettings) -> Affine {
+        let (width, height) = (self.width(), self.height());
+        let (page, placed) = match (settings.paper.map(|p| p.size()), settings.rotate) {
+            (Some(paper), false) => (paper, Size::new(width, height)),
+            (Some(paper), true) => (
+                Size::new(paper.height, paper.width),
+                Size::new(height, width),
+            ),
+            (None, false) => (Size::new(width, height), Size::new(width, height)),
+            (None, true) => (Size::new(height, width), Size::new(height, width)),
+        };
+        let margin = (page - placed) / 2.;
+
+        let to_page = if settings.rotate {
+            Affine::new([0., 1., 1., 0., margin.width, margin.height])
+        } else {
+            Affine::new([1., 0., 0., -1., margin.width, margin.height + height])
+        };
+        let scale = PLOTTER_UNITS_PER_MM / MM;
+        Affine::translate(settings.origin.to_vec2() * PLOTTER_UNITS_PER_MM)
+            * Affine::scale(scale)
+            * to_page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Line};
+    use crate::pen::{Color, Pen};
+
+    /// Splits an HPGL stream into commands and their parameters
+    fn parse(hpgl: &str) -> Vec<(String, Vec<f64>)> {
+        hpgl.split(';')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                let (name, params) = c.split_at(2);
+                let params = params
+                    .split(',')
+                    .filter(|p| !p.is_empty())
+                    .map(|p| p.parse().unwrap())
+                    .collect();
+                (name.to_string(), params)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pens_and_moves() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(10. * MM, 20. * MM));
+        canvas.add(Line::new(point(0, 0), point(10. * MM, 0)).unwrap());
+        canvas.add_with_pen(
+            Pen::new("red", Color::RED),
+            Line::new(point(0, 20. * MM), point(10. * MM, 10. * MM)).unwrap(),
+        );
+
+        let settings = HpglSettings {
+            velocity: Some(10.),
+            ..Default::default()
+        };
+        let commands = parse(&canvas.render_hpgl_string(&settings));
+        let names: Vec<&str> = commands.iter().map(|(c, _)| c.as_str()).collect();
+        assert_eq!(
+            names,
+            ["IN", "SP", "VS", "PU", "PD", "SP", "VS", "PU", "PD", "PU", "SP"]
+        );
+        assert_eq!(commands[1].1, [1.]);
+        assert_eq!(commands[5].1, [2.]);
+        assert_eq!(commands[10].1, [0.]);
+
+        // The top of the canvas is the top of the page
+        assert_eq!(commands[3].1, [0., 800.]);
+        assert_eq!(commands[4].1, [400., 800.]);
+        assert_eq!(commands[7].1, [0., 0.]);
+        assert_eq!(commands[8].1, [400., 400.]);
+    }
+
+    #[test]
+    fn rotated_on_paper() {
+        let mut canvas = Canvas::from_paper(PaperSize::A4);
+        canvas.add(Line::new(point(0, 0), point(10. * MM, 0)).unwrap());
+
+        let settings = HpglSettings {
+            origin: point(5, 5),
+            rotate: true,
+            paper: Some(PaperSize::A3),
+            velocity: None,
+        };
+        let commands = parse(&canvas.render_hpgl_string(&settings));
+
+        // The canvas is turned to lie along the A3 page's long edge, then
+        // centered on it
+        let x = 5. * 40. + (420. - 297.) / 2. * 40.;
+        let y = 5. * 40. + (297. - 210.) / 2. * 40.;
+        assert_eq!(commands[2].1, [x, y]);
+        assert_eq!(commands[3].1, [x, y + 400.]);
+    }
+}
@@ -1,10 +1,19 @@
-use crate::geometry::GeomError;
+use crate::geometry::{GeomError, Point};
 use std::error;
 use std::fmt;
+use std::io;
 
 mod egui;
+mod gcode;
+mod hpgl;
+mod pdf;
+mod png;
 mod svg;
 pub use self::egui::EguiRenderer;
+pub use self::gcode::{GcodeRenderer, GcodeSettings, PenLift};
+pub use self::hpgl::{HpglRenderer, HpglSettings, PLOTTER_UNITS_PER_MM};
+pub use self::pdf::{PdfPages, PdfRenderer};
+pub use self::png::{PngRenderer, PngSettings};
 pub use self::svg::SvgRenderer;
 
 pub type RenderResult<T> = Result<T, RenderError>;
@@ -12,12 +21,20 @@ pub type RenderResult<T> = Result<T, RenderError>;
 #[derive(Debug)]
 pub enum RenderError {
     BaseGeometry(GeomError),
+    Io(io::Error),
+    /// A point, in the output's own units, lies outside of the area that can
+    /// be drawn on
+    OutOfBounds(Point),
 }
 
 impl fmt::Display for RenderError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::BaseGeometry(e) => write!(f, "error with base geometry: {}", e),
+            Self::Io(e) => write!(f, "unable to write output: {}", e),
+            Self::OutOfBounds(p) => {
+                write!(f, "({:.3}, {:.3}) lies outside of the work area", p.x, p.y)
+            }
         }
     }
 }
@@ -28,4 +45,18 @@ impl From<GeomError> for RenderError {
     }
 }
 
-impl error::Error for RenderError {}
+impl From<io::Error> for RenderError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::BaseGeometry(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::OutOfBounds(_) => None,
+        }
+    }
+}
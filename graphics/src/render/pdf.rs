@@ -0,0 +1,256 @@
+use crate::canvas::*;
+use crate::geometry::{PathEl, Point, Shape};
+use crate::pen::Pen;
+use crate::render::RenderResult;
+use crate::units::{Size, PT};
+use std::fmt::Write as FmtWrite;
+use std::io::Write;
+
+/// How a canvas is divided into the pages of a PDF
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PdfPages {
+    /// The whole canvas on a single page
+    Single,
+    /// One page per pen, each the size of the canvas
+    PerLayer,
+    /// One page per top-level sub-canvas, each the size of its sub-canvas,
+    /// preceded by a page of any shapes added to the canvas directly
+    PerSubcanvas,
+}
+
+pub trait PdfRenderer {
+    /// Writes the canvas as a vector PDF, with pages sized to match it
+    fn render_pdf_to_writer(&self, writer: impl Write, pages: PdfPages) -> RenderResult<()>;
+
+    /// Returns the canvas as a vector PDF, with pages sized to match it
+    fn render_pdf_bytes(&self, pages: PdfPages) -> Vec<u8>;
+
+    /// Saves the canvas as a vector PDF file, with pages sized to match it
+    fn render_pdf(&self, path: &str, pages: PdfPages) -> RenderResult<()>;
+}
+
+impl PdfRenderer for Canvas {
+    fn render_pdf_to_writer(&self, mut writer: impl Write, pages: PdfPages) -> RenderResult<()> {
+        Ok(writer.write_all(&self.render_pdf_bytes(pages))?)
+    }
+
+    fn render_pdf_bytes(&self, pages: PdfPages) -> Vec<u8> {
+        let size = Size::new(self.width(), self.height());
+        let mut pages: Vec<(Size, String)> = match pages {
+            PdfPages::Single => vec![(size, page_contents(size, &self.layers()))],
+            PdfPages::PerLayer => self
+                .layers()
+                .into_iter()
+                .map(|layer| (size, page_contents(size, &[layer])))
+                .collect(),
+            PdfPages::PerSubcanvas => {
+                let clipped = self.clipped();
+                let mut own = Canvas::new(Point::ZERO, size);
+                let mut pages = vec![];
+                for element in clipped.elements() {
+                    match element {
+                        CanvasElement::Canvas(c) => {
+                            let size = Size::new(c.width(), c.height());
+                            pages.push((size, page_contents(size, &c.layers())));
+                        }
+                        CanvasElement::Shape(s, pen) | CanvasElement::Opaque(s, pen) => {
+                            own.add_with_pen(pen.clone(), s.clone())
+                        }
+                    }
+                }
+                if !own.elements().is_empty() {
+                    pages.insert(0, (size, page_contents(size, &own.layers())));
+                }
+                pages
+            }
+        };
+        // Viewers reject documents without pages, so an empty canvas still
+        // gets a blank one
+        if pages.is_empty() {
+            pages.push((size, page_contents(size, &[])));
+        }
+        write_document(&pages)
+    }
+
+    fn render_pdf(&self, path: &str, pages: PdfPages) -> RenderResult<()> {
+        self.render_pdf_to_writer(std::fs::File::create(path)?, pages)
+    }
+}
+
+/// Writes the content stream of a page of `size` canvas units, drawing
+/// `layers` in order
+fn page_contents(size: Size, layers: &[(Pen, Vec<Shape>)]) -> String {
+    let mut out = String::new();
+    // PDF measures in points from the bottom left of the page, so the
+    // canvas is scaled and flipped to keep its own coordinates
+    writeln!(out, "1 J 1 j").unwrap();
+    writeln!(
+        out,
+        "{} 0 0 {} 0 {} cm",
+        1. / PT,
+        -1. / PT,
+        size.height / PT
+    )
+    .unwrap();
+    for (pen, shapes) in layers {
+        let color = pen.color;
+        writeln!(
+            out,
+            "{} {} {} RG",
+            color.r as f64 / 255.,
+            color.g as f64 / 255.,
+            color.b as f64 / 255.
+        )
+        .unwrap();
+        for shape in shapes {
+            writeln!(out, "{} w", shape.stroke()).unwrap();
+            let mut last = Point::ZERO;
+            for el in shape.to_path().inner().elements() {
+                match el {
+                    PathEl::MoveTo(p) => writeln!(out, "{} {} m", p.x, p.y),
+                    PathEl::LineTo(p) => writeln!(out, "{} {} l", p.x, p.y),
+                    // PDF only has cubic curves, which can represent any
+                    // quadratic exactly
+                    PathEl::QuadTo(c, p) => {
+                        let c1 = last + (*c - last) * (2. / 3.);
+                        let c2 = *p + (*c - *p) * (2. / 3.);
+                        writeln!(out, "{} {} {} {} {} {} c", c1.x, c1.y, c2.x, c2.y, p.x, p.y)
+                    }
+                    PathEl::CurveTo(c1, c2, p) => {
+                        writeln!(out, "{} {} {} {} {} {} c", c1.x, c1.y, c2.x, c2.y, p.x, p.y)
+                    }
+                    PathEl::ClosePath => writeln!(out, "h"),
+                }
+                .unwrap();
+                last = match el {
+                    PathEl::MoveTo(p)
+                    | PathEl::LineTo(p)
+                    | PathEl::QuadTo(_, p)
+                    | PathEl::CurveTo(_, _, p) => *p,
+                    PathEl::ClosePath => last,
+                };
+            }
+            writeln!(out, "S").unwrap();
+        }
+    }
+    out
+}
+
+/// Writes a PDF of pages of the given size, in canvas units, and content
+fn write_document(pages: &[(Size, String)]) -> Vec<u8> {
+    // The catalog and page tree come first, followed by a page object and
+    // content stream for each page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|n| 3 + n * 2).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+    ];
+    for ((size, contents), id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R >>",
+            size.width / PT,
+            size.height / PT,
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            contents.len(),
+            contents
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = vec![];
+    for (n, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        write!(out, "{} 0 obj\n{}\nendobj\n", n + 1, object).unwrap();
+    }
+    let xref = out.len();
+    write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).unwrap();
+    for offset in offsets {
+        writeln!(out, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Circle, Line};
+    use crate::pen::Color;
+    use crate::units::INCH;
+
+    /// Checks that every entry in the cross-reference table points at the
+    /// start of its object
+    fn check_xref(pdf: &str) {
+        let start: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        let entries = pdf[start..].lines().skip(3);
+        for (n, entry) in entries.take_while(|l| l.ends_with(" n ")).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", n + 1)));
+        }
+    }
+
+    #[test]
+    fn single_page() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(8.5 * INCH, 11. * INCH));
+        canvas.add(Circle::new(point(100, 100), 50.));
+        canvas.add_with_pen(
+            Pen::new("red", Color::RED),
+            Line::new(point(0, 0), point(10, 10)).unwrap(),
+        );
+        let pdf = String::from_utf8(canvas.render_pdf_bytes(PdfPages::Single)).unwrap();
+        check_xref(&pdf);
+        assert!(pdf.contains("/Count 1"));
+        assert!(pdf.contains("/MediaBox [0 0 612 792]"));
+        // Curves are kept as curves
+        assert!(pdf.contains(" c\n"));
+        assert!(pdf.contains("1 0 0 RG\n"));
+    }
+
+    #[test]
+    fn page_per_subcanvas_and_layer() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add_with_pen(
+            Pen::new("red", Color::RED),
+            Line::new(point(0, 0), point(10, 10)).unwrap(),
+        );
+        for mut tile in canvas.tiled_subcanvases(2, 1, 0.) {
+            tile.add(Circle::new(point(0, 0), 10.));
+            canvas.add(tile);
+        }
+
+        let pdf = String::from_utf8(canvas.render_pdf_bytes(PdfPages::PerSubcanvas)).unwrap();
+        check_xref(&pdf);
+        assert!(pdf.contains("/Count 3"));
+        assert!(pdf.contains("/MediaBox [0 0 37.5 75]"));
+
+        let pdf = String::from_utf8(canvas.render_pdf_bytes(PdfPages::PerLayer)).unwrap();
+        check_xref(&pdf);
+        assert!(pdf.contains("/Count 2"));
+
+        // Empty canvases still get a blank page of their size
+        let empty = Canvas::new(point(0, 0), Size::new(100., 100.));
+        for pages in [PdfPages::PerLayer, PdfPages::PerSubcanvas] {
+            let pdf = String::from_utf8(empty.render_pdf_bytes(pages)).unwrap();
+            check_xref(&pdf);
+            assert!(pdf.contains("/Count 1"));
+            assert!(pdf.contains("/MediaBox [0 0 75 75]"));
+        }
+    }
+}
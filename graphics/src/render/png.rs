@@ -0,0 +1,256 @@
+use crate::canvas::*;
+use crate::geometry::Point;
+use crate::pen::Color;
+use crate::render::RenderResult;
+use crate::units::DPI;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Settings for `PngRenderer`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PngSettings {
+    /// Output resolution. Canvas units are 1/96in, so the default draws one
+    /// pixel per canvas unit.
+    pub dpi: f64,
+    /// Color the image is filled with before drawing, or transparent if not
+    /// given
+    pub background: Option<Color>,
+    /// Simulates ink by drawing each stroke with this opacity, so that color
+    /// builds up where strokes overlap as it would on paper. Strokes are
+    /// drawn fully opaque if not given.
+    pub ink: Option<f64>,
+}
+
+impl Default for PngSettings {
+    fn default() -> Self {
+        Self {
+            dpi: DPI,
+            background: Some(Color::WHITE),
+            ink: None,
+        }
+    }
+}
+
+pub trait PngRenderer {
+    /// Writes the canvas as an anti-aliased PNG image
+    fn render_png_to_writer(&self, writer: impl Write, settings: &PngSettings) -> RenderResult<()>;
+
+    /// Returns the canvas as an anti-aliased PNG image
+    fn render_png_bytes(&self, settings: &PngSettings) -> Vec<u8>;
+
+    /// Saves the canvas as an anti-aliased PNG image
+    fn render_png(&self, path: &str, settings: &PngSettings) -> RenderResult<()>;
+}
+
+impl PngRenderer for Canvas {
+    fn render_png_to_writer(&self, writer: impl Write, settings: &PngSettings) -> RenderResult<()> {
+        let scale = settings.dpi / DPI;
+        let mut image = Image::new(
+            ((self.width() * scale).ceil() as u32).max(1),
+            ((self.height() * scale).ceil() as u32).max(1),
+            settings.background,
+        );
+        for (pen, shapes) in self.layers() {
+            for shape in shapes {
+                let width = shape.stroke() * scale;
+                for points in shape.inner().to_points() {
+                    let points: Vec<Point> = points
+                        .iter()
+                        .map(|p| Point::new(p.x * scale, p.y * scale))
+                        .collect();
+                    image.stroke(&points, width, pen.color, settings.ink.unwrap_or(1.));
+                }
+            }
+        }
+        image.encode(writer)
+    }
+
+    fn render_png_bytes(&self, settings: &PngSettings) -> Vec<u8> {
+        let mut png = vec![];
+        self.render_png_to_writer(&mut png, settings)
+            .expect("encoding a PNG in memory can't fail");
+        png
+    }
+
+    fn render_png(&self, path: &str, settings: &PngSettings) -> RenderResult<()> {
+        self.render_png_to_writer(BufWriter::new(File::create(path)?), settings)
+    }
+}
+
+/// An RGBA image with straight alpha, eight bits per channel
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32, background: Option<Color>) -> Self {
+        let fill = match background {
+            Some(c) => [c.r, c.g, c.b, 255],
+            None => [0; 4],
+        };
+        Self {
+            width,
+            height,
+            pixels: fill.repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Draws a polyline with round caps and joins, anti-aliased by the
+    /// fraction of each pixel it covers
+    fn stroke(&mut self, points: &[Point], width: f64, color: Color, opacity: f64) {
+        if points.is_empty() {
+            return;
+        }
+        let (image_width, image_height) = (self.width as usize, self.height as usize);
+        let reach = width / 2. + 1.;
+        let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for p in points {
+            x0 = x0.min(p.x - reach);
+            y0 = y0.min(p.y - reach);
+            x1 = x1.max(p.x + reach);
+            y1 = y1.max(p.y + reach);
+        }
+        let clamp_x = |x: f64| (x.floor().max(0.) as usize).min(image_width);
+        let clamp_y = |y: f64| (y.floor().max(0.) as usize).min(image_height);
+        let (left, top) = (clamp_x(x0), clamp_y(y0));
+        let (right, bottom) = (clamp_x(x1) + 1, clamp_y(y1) + 1);
+        let (right, bottom) = (right.min(image_width), bottom.min(image_height));
+        if left >= right || top >= bottom {
+            return;
+        }
+
+        // Coverage is collected for the whole stroke before compositing, so
+        // that its own joins don't build up ink
+        let mask_width = right - left;
+        let mut mask = vec![0u8; mask_width * (bottom - top)];
+        let segments: Vec<(Point, Point)> = if points.len() == 1 {
+            vec![(points[0], points[0])]
+        } else {
+            points.windows(2).map(|w| (w[0], w[1])).collect()
+        };
+        for (a, b) in segments {
+            let sx = clamp_x(a.x.min(b.x) - reach).max(left);
+            let ex = (clamp_x(a.x.max(b.x) + reach) + 1).min(right);
+            let sy = clamp_y(a.y.min(b.y) - reach).max(top);
+            let ey = (clamp_y(a.y.max(b.y) + reach) + 1).min(bottom);
+            for y in sy..ey {
+                for x in sx..ex {
+                    let d = distance(Point::new(x as f64 + 0.5, y as f64 + 0.5), a, b);
+                    // Hairlines narrower than a pixel are drawn fainter
+                    let coverage = (width / 2. + 0.5 - d).min(width).clamp(0., 1.);
+                    let m = &mut mask[(y - top) * mask_width + (x - left)];
+                    *m = (*m).max((coverage * 255.).round() as u8);
+                }
+            }
+        }
+
+        let rgb = [color.r as f32, color.g as f32, color.b as f32];
+        let ink = opacity as f32 / 255.;
+        for y in top..bottom {
+            for x in left..right {
+                let alpha = mask[(y - top) * mask_width + (x - left)] as f32 * ink;
+                if alpha <= 0. {
+                    continue;
+                }
+                let i = (y * image_width + x) * 4;
+                let pixel = &mut self.pixels[i..i + 4];
+                let below = pixel[3] as f32 / 255. * (1. - alpha);
+                let total = alpha + below;
+                for c in 0..3 {
+                    let blended = (rgb[c] * alpha + pixel[c] as f32 * below) / total;
+                    pixel[c] = blended.round() as u8;
+                }
+                pixel[3] = (total * 255.).round() as u8;
+            }
+        }
+    }
+
+    fn encode(&self, writer: impl Write) -> RenderResult<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .map_err(|err| match err {
+                png::EncodingError::IoError(err) => err.into(),
+                err => io::Error::new(io::ErrorKind::InvalidData, err).into(),
+            })
+    }
+}
+
+/// Returns the distance from `p` to the segment `a`→`b`
+fn distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len2 = ab.hypot2();
+    let t = if len2 == 0. {
+        0.
+    } else {
+        ((p - a).dot(ab) / len2).clamp(0., 1.)
+    };
+    p.distance(a + ab * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Line};
+    use crate::units::Size;
+
+    /// Decodes a PNG into its size and RGBA pixels
+    fn decode(png: &[u8]) -> (usize, usize, Vec<[u8; 4]>) {
+        let mut reader = png::Decoder::new(png).read_info().unwrap();
+        let mut raw = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut raw).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        let pixels = raw[..info.buffer_size()]
+            .chunks(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
+            .collect();
+        (info.width as usize, info.height as usize, pixels)
+    }
+
+    #[test]
+    fn strokes_and_ink() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(40., 20.));
+        let mut line = Line::new(point(5, 5.25), point(35, 5.25)).unwrap();
+        line.set_stroke_width(3.);
+        canvas.add(line);
+        canvas.add(line);
+
+        let (width, height, pixels) = decode(&canvas.render_png_bytes(&PngSettings {
+            dpi: DPI * 2.,
+            ..Default::default()
+        }));
+        assert_eq!((width, height), (80, 40));
+        assert_eq!(pixels[11 * width + 40], [0, 0, 0, 255]);
+        assert_eq!(pixels[30 * width + 40], [255, 255, 255, 255]);
+        // Anti-aliased edges are partially covered
+        let edge = pixels[7 * width + 40][0];
+        assert!(edge > 0 && edge < 255);
+
+        let ink = PngSettings {
+            background: None,
+            ink: Some(0.5),
+            ..Default::default()
+        };
+        let (width, _, pixels) = decode(&canvas.render_png_bytes(&ink));
+        // The two overlapping strokes build up to 75% coverage, give or take
+        // the rounding of the first one to eight bits
+        let [r, g, b, a] = pixels[5 * width + 20];
+        assert_eq!([r, g, b], [0, 0, 0]);
+        assert!((191..=192).contains(&a));
+        assert_eq!(pixels[15 * width + 20], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn compresses() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(1000., 1000.));
+        canvas.add(Line::new(point(0, 500), point(1000, 500)).unwrap());
+        // Mostly blank images compress to a small fraction of their 4MB of
+        // pixels
+        assert!(canvas.render_png_bytes(&PngSettings::default()).len() < 100_000);
+    }
+}
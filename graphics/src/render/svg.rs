@@ -1,59 +1,154 @@
 use crate::canvas::*;
-use crate::geometry::{Circle, PathEl, Point, Shape, Shaped};
-use svg::node::element::{path::Data, Circle as SvgCircle, Path as SvgPath};
+use crate::geometry::{Affine, Circle, PathEl, Point, Shape, Shaped};
+use crate::pen::Pen;
+use crate::render::RenderResult;
+use std::io::Write;
+use svg::node::element::{path::Data, Circle as SvgCircle, Group, Path as SvgPath};
 use svg::Document;
 
+const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+
 pub trait SvgRenderer {
-    fn render_svg(&self, path: &str);
+    /// Writes the canvas as an SVG document, with one Inkscape layer per pen
+    fn render_svg_to_writer(&self, writer: impl Write) -> RenderResult<()>;
+
+    /// Returns the canvas as an SVG document, with one Inkscape layer per pen
+    fn render_svg_string(&self) -> String;
+
+    /// Saves the canvas as an SVG file, with one Inkscape layer per pen
+    fn render_svg(&self, path: &str) -> RenderResult<()>;
+
+    /// Saves each of the canvas's pens to its own SVG file, named after
+    /// `path` with the pen name appended to the file stem, e.g. `out-red.svg`.
+    /// Pens that share a name also have their color appended, e.g.
+    /// `out-red-000000.svg`.
+    fn render_svg_per_pen(&self, path: &str) -> RenderResult<()>;
 }
 
 impl SvgRenderer for Canvas {
-    fn render_svg(&self, path: &str) {
-        let doc = Document::new()
-            .set("width", self.width())
-            .set("height", self.height());
-        let rendered_doc = self.render(doc);
-        svg::save(path.to_string(), &rendered_doc).expect("Unable to save SVG");
+    fn render_svg_to_writer(&self, writer: impl Write) -> RenderResult<()> {
+        Ok(svg::write(writer, &self.layered_document())?)
+    }
+
+    fn render_svg_string(&self) -> String {
+        self.layered_document().to_string()
+    }
+
+    fn render_svg(&self, path: &str) -> RenderResult<()> {
+        Ok(svg::save(path, &self.layered_document())?)
+    }
+
+    fn render_svg_per_pen(&self, path: &str) -> RenderResult<()> {
+        let path = std::path::Path::new(path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let clipped = self.clipped();
+        let pens = clipped.pens();
+        for pen in &pens {
+            let doc = self
+                .document()
+                .add(clipped.render_contents(pen, Affine::default()));
+            let name = if pens.iter().filter(|p| p.name == pen.name).count() > 1 {
+                format!("{}-{}", pen.name, &pen.color.to_hex()[1..])
+            } else {
+                pen.name.clone()
+            };
+            let pen_path = path.with_file_name(format!("{}-{}.svg", stem, name));
+            svg::save(pen_path, &doc)?;
+        }
+        Ok(())
     }
 }
 
-pub trait SvgRenderable {
-    fn render(&self, doc: Document) -> Document;
+trait SvgRenderable {
+    /// Adds the parts of the element drawn with `pen` to `group`, mapped by
+    /// `transform` into the coordinates of the canvas being rendered
+    fn render(&self, group: Group, pen: &Pen, transform: Affine) -> Group;
 }
 
 impl SvgRenderable for CanvasElement {
-    fn render(&self, doc: Document) -> Document {
+    fn render(&self, group: Group, pen: &Pen, transform: Affine) -> Group {
         match self {
-            Self::Canvas(c) => c.render(doc),
-            Self::Shape(s) => s.render(doc),
+            Self::Canvas(c) => c.render(group, pen, transform),
+            Self::Shape(s, p) | Self::Opaque(s, p) if p == pen => s.render(group, p, transform),
+            Self::Shape(..) | Self::Opaque(..) => group,
         }
     }
 }
 
+impl Canvas {
+    /// Creates a document at the canvas's physical size, with a view box
+    /// that maps canvas units onto it
+    fn document(&self) -> Document {
+        let unit = self.unit();
+        // Rounded so that sizes like 210mm don't pick up float noise
+        let dimension = |n: f64| unit.to_string_with_val((n / unit.scale() * 1e6).round() / 1e6);
+        Document::new()
+            .set("xmlns:inkscape", INKSCAPE_NS)
+            .set("width", dimension(self.width()))
+            .set("height", dimension(self.height()))
+            .set("viewBox", (0., 0., self.width(), self.height()))
+    }
+
+    fn layered_document(&self) -> Document {
+        let clipped = self.clipped();
+        clipped
+            .pens()
+            .iter()
+            .enumerate()
+            .map(|(i, pen)| {
+                clipped
+                    .render_contents(pen, Affine::default())
+                    .set("inkscape:groupmode", "layer")
+                    .set("inkscape:label", pen.name.as_str())
+                    .set("id", format!("layer{}", i + 1))
+            })
+            .fold(self.document(), |doc, layer| doc.add(layer))
+    }
+
+    /// Renders the canvas's elements drawn with `pen` into a group, mapped
+    /// by `transform`. Clipping should already have been applied with
+    /// `Canvas::clipped`.
+    fn render_contents(&self, pen: &Pen, transform: Affine) -> Group {
+        self.elements()
+            .iter()
+            .fold(Group::new(), |acc, c| c.render(acc, pen, transform))
+    }
+}
+
 impl SvgRenderable for Canvas {
-    fn render(&self, doc: Document) -> Document {
-        // [Res<Vec>, Res<Vec>]
-        self.elements().iter().fold(doc, |acc, c| c.render(acc))
+    /// Sub-canvas transforms are applied to the geometry rather than written
+    /// as group transforms, which would also scale stroke widths, so that
+    /// strokes are as wide as they are in `Canvas::layers`
+    fn render(&self, group: Group, pen: &Pen, transform: Affine) -> Group {
+        if !self.pens().contains(pen) {
+            return group;
+        }
+        group.add(self.render_contents(pen, transform * self.transform()))
     }
 }
 
 impl SvgRenderable for Circle {
-    fn render(&self, doc: Document) -> Document {
+    fn render(&self, group: Group, pen: &Pen, _transform: Affine) -> Group {
         let c = SvgCircle::new()
             .set("fill", "none")
-            .set("stroke", "black")
+            .set("stroke", pen.color.to_hex())
             .set("stroke-width", self.stroke())
             .set("cx", self.center().x)
             .set("cy", self.center().y)
             .set("r", self.radius());
-        doc.add(c)
+        group.add(c)
     }
 }
 
 impl SvgRenderable for Shape {
-    fn render(&self, doc: Document) -> Document {
+    fn render(&self, group: Group, pen: &Pen, transform: Affine) -> Group {
+        if transform != Affine::default() {
+            return self
+                .transform(transform)
+                .render(group, pen, Affine::default());
+        }
         match self {
-            Self::Circle(c) => c.render(doc),
+            Self::Circle(c) => c.render(group, pen, transform),
             _ => {
                 fn t(point: &Point) -> (f64, f64) {
                     (point.x, point.y)
@@ -71,10 +166,10 @@ impl SvgRenderable for Shape {
                     }
                 }
 
-                doc.add(
+                group.add(
                     SvgPath::new()
                         .set("fill", "none")
-                        .set("stroke", "black")
+                        .set("stroke", pen.color.to_hex())
                         .set("stroke-width", self.stroke())
                         .set("fill-rule", "evenodd")
                         .set("d", d),
@@ -83,3 +178,75 @@ impl SvgRenderable for Shape {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Line};
+    use crate::pen::Color;
+    use crate::render::RenderError;
+    use crate::units::{PaperSize, Size, Unit, MM};
+
+    #[test]
+    fn renders_to_writer() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(10., 10.));
+        canvas.add(Circle::new(point(5, 5), 2.));
+
+        let mut buf = vec![];
+        canvas.render_svg_to_writer(&mut buf).unwrap();
+        let svg = String::from_utf8(buf).unwrap();
+        assert_eq!(svg, canvas.render_svg_string());
+        assert!(svg.contains("<circle"));
+        assert!(svg.contains(r#"width="0.104167in""#));
+
+        let missing = canvas.render_svg("/nonexistent/dir/out.svg");
+        assert!(matches!(missing, Err(RenderError::Io(_))));
+    }
+
+    #[test]
+    fn physical_size() {
+        let mut canvas = Canvas::from_paper(PaperSize::A4);
+        let svg = canvas.render_svg_string();
+        assert!(svg.contains(r#"width="210mm""#) && svg.contains(r#"height="297mm""#));
+        assert!(svg.contains(&format!(r#"viewBox="0 0 {} {}""#, 210. * MM, 297. * MM)));
+
+        canvas.set_unit(Unit::Cm);
+        assert!(canvas.render_svg_string().contains(r#"width="21cm""#));
+    }
+
+    #[test]
+    fn sub_canvas_strokes() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        let mut sub = Canvas::new(point(0, 0), Size::new(50., 50.));
+        sub.set_transform(Affine::scale(2.));
+        sub.add(Line::new(point(0, 0), point(10, 10)).unwrap());
+        sub.add(Circle::new(point(20, 20), 5.));
+        canvas.add(sub);
+
+        // Transforms are baked into the geometry instead of scaling strokes
+        let svg = canvas.render_svg_string();
+        assert!(!svg.contains("transform="));
+        let widths: Vec<f64> = svg
+            .split(r#"stroke-width=""#)
+            .skip(1)
+            .map(|w| w[..w.find('"').unwrap()].parse().unwrap())
+            .collect();
+        let layer_widths: Vec<f64> = canvas.layers()[0].1.iter().map(|s| s.stroke()).collect();
+        assert_eq!(widths, layer_widths);
+        assert!(svg.contains(r#"r="10""#));
+    }
+
+    #[test]
+    fn pens_sharing_a_name() {
+        let mut canvas = Canvas::new(point(0, 0), Size::new(100., 100.));
+        canvas.add_with_pen(Pen::new("red", Color::RED), Circle::new(point(10, 10), 5.));
+        canvas.add_with_pen(Pen::new("red", Color::BLUE), Circle::new(point(20, 10), 5.));
+
+        // Each pen gets its own layer in its own color, as in `layers`
+        let svg = canvas.render_svg_string();
+        let layers: Vec<&str> = svg.split(r#"inkscape:label="red""#).skip(1).collect();
+        assert_eq!(layers.len(), 2);
+        assert!(layers[0].contains("#ff0000") && !layers[0].contains("#0000ff"));
+        assert!(layers[1].contains("#0000ff"));
+    }
+}
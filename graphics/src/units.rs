@@ -1,8 +1,9 @@
 use std::fmt;
+use std::str::FromStr;
 
 pub use kurbo::{Point, Size, Vec2};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Unit {
     Px,
     Mm,
@@ -46,3 +47,96 @@ impl fmt::Display for Unit {
         )
     }
 }
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "px" => Ok(Self::Px),
+            "mm" => Ok(Self::Mm),
+            "cm" => Ok(Self::Cm),
+            "in" => Ok(Self::In),
+            _ => Err(format!("unknown unit '{}', expected px, mm, cm or in", s)),
+        }
+    }
+}
+
+/// Common paper sizes that a canvas can be created from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PaperSize {
+    /// 297mm × 420mm
+    A3,
+    /// 210mm × 297mm
+    A4,
+    /// 8.5in × 11in
+    Letter,
+    /// 11in × 17in
+    Tabloid,
+    /// 6in × 5in
+    Postcard,
+}
+
+impl PaperSize {
+    pub const ALL: [PaperSize; 5] = [
+        Self::A3,
+        Self::A4,
+        Self::Letter,
+        Self::Tabloid,
+        Self::Postcard,
+    ];
+
+    /// Returns the paper's dimensions in canvas units, in the orientation it
+    /// is usually described in
+    pub fn size(&self) -> Size {
+        match self {
+            Self::A3 => Size::new(297. * MM, 420. * MM),
+            Self::A4 => Size::new(210. * MM, 297. * MM),
+            Self::Letter => Size::new(8.5 * INCH, 11. * INCH),
+            Self::Tabloid => Size::new(11. * INCH, 17. * INCH),
+            Self::Postcard => Size::new(6. * INCH, 5. * INCH),
+        }
+    }
+
+    /// Returns the paper's dimensions turned a quarter turn from `size`
+    pub fn rotated_size(&self) -> Size {
+        let size = self.size();
+        Size::new(size.height, size.width)
+    }
+
+    /// Returns the unit that the paper size is defined in
+    pub fn unit(&self) -> Unit {
+        match self {
+            Self::A3 | Self::A4 => Unit::Mm,
+            Self::Letter | Self::Tabloid | Self::Postcard => Unit::In,
+        }
+    }
+}
+
+impl FromStr for PaperSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|p| p.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown paper size '{}'", s))
+    }
+}
+
+impl fmt::Display for PaperSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::A3 => "A3",
+                Self::A4 => "A4",
+                Self::Letter => "Letter",
+                Self::Tabloid => "Tabloid",
+                Self::Postcard => "Postcard",
+            }
+        )
+    }
+}
@@ -6,3 +6,5 @@ mod metadata;
 pub use metadata::*;
 mod sketches;
 pub use sketches::*;
+mod snapshot;
+pub use snapshot::*;
@@ -3,6 +3,13 @@ use rand::prelude::*;
 use rand_pcg::Pcg64;
 use std::f64::consts::{PI, TAU};
 
+/// The name the title font is looked up by in the font registry. Unless a
+/// font has already been registered under it, the system's DejaVu Serif
+/// Condensed Bold Italic is read and registered as it.
+pub const CHARCOAL_FONT: &str = "Charcoal Title";
+
+const SYSTEM_FONT: &str = "/usr/share/fonts/TTF/DejaVuSerifCondensed-BoldItalic.ttf";
+
 /// A set of concentric chaotic semi-circles designed to be scratched in
 /// to a paper covered with charcoal powder
 #[sketch]
@@ -47,11 +54,9 @@ impl Charcoal {}
 
 impl Sketch for Charcoal {
     fn exec(&self) -> SketchResult<Canvas> {
-        const WIDTH: f64 = 11. * INCH;
-        const HEIGHT: f64 = 17. * INCH;
-        let mut canvas = Canvas::new(point(0, 0), Size::new(WIDTH, HEIGHT));
+        let mut canvas = Canvas::from_paper(PaperSize::Tabloid);
         let margin = self.margin * INCH;
-        let _width_adj = WIDTH - 2. * margin;
+        let _width_adj = canvas.width() - 2. * margin;
 
         let mut rng = Pcg64::seed_from_u64(self.seed);
         let circ_origin = point(self.circle_origin_x, self.circle_origin_y);
@@ -74,12 +79,21 @@ impl Sketch for Charcoal {
             canvas.add(p);
         }
 
+        if load_font(CHARCOAL_FONT).is_err() {
+            let bytes = std::fs::read(SYSTEM_FONT).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("unable to read font '{}': {}", SYSTEM_FONT, e),
+                )
+            });
+            register_font(CHARCOAL_FONT, bytes.map_err(GeomError::from)?)?;
+        }
         let text = TextBuilder::new()
             .origin(point(self.text_x, self.text_y))
             .line_padding(self.text_padding)
             .size(self.text_size)
             .text_line("DEFLECTOR.")
-            .font("/usr/share/fonts/TTF/DejaVuSerifCondensed-BoldItalic.ttf")
+            .font(CHARCOAL_FONT)
             .build()?;
 
         canvas.add(text);
@@ -103,7 +103,7 @@ impl Sketch for Manifold {
         const HEIGHT: f64 = 17. * INCH;
         let mut rng = Pcg64::seed_from_u64(self.seed);
 
-        let mut canvas = Canvas::new(point(0, 0), Size::new(WIDTH, HEIGHT));
+        let mut canvas = Canvas::from_paper(PaperSize::Tabloid);
         let center = point(WIDTH / 2., HEIGHT / 2.);
 
         for n in 0..self.flower_count {
@@ -9,3 +9,5 @@ sketchlist! {
     postcard::Postcard,
     weather::Weather,
 }
+
+pub use charcoal::CHARCOAL_FONT;
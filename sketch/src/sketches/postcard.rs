@@ -86,11 +86,9 @@ impl Postcard {
 
 impl Sketch for Postcard {
     fn exec(&self) -> SketchResult<Canvas> {
-        const WIDTH: f64 = 6. * INCH;
-        const HEIGHT: f64 = 5. * INCH;
-        let mut canvas = Canvas::new(point(0, 0), Size::new(WIDTH, HEIGHT));
+        let mut canvas = Canvas::from_paper(PaperSize::Postcard);
         let margin = self.margin * INCH;
-        let width_adj = WIDTH - 2. * margin;
+        let width_adj = canvas.width() - 2. * margin;
 
         let mut waves: Vec<Vec<Point>> = vec![vec![]; self.sine_waves as usize];
 
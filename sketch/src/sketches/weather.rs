@@ -81,28 +81,36 @@ impl Sketch for Weather {
         const WIDTH: f64 = 11. * INCH;
         const HEIGHT: f64 = 17. * INCH;
 
-        let mut canvas = Canvas::new(point(0, 0), Size::new(WIDTH, HEIGHT));
+        let mut canvas = Canvas::from_paper(PaperSize::Tabloid);
         let center = point(WIDTH / 2., HEIGHT / 2. - self.center_y_offset * INCH);
 
-        let text_lines: Vec<&'static str> = vec![
-            //"a drop on the",
-            "a surface",
-            "once untouched",
-            "  by our storm.",
-            "longing for",
-            "  the art of",
-            "    interference",
+        // Each line is indented by a number of steps, each as wide as two
+        // spaces
+        let text_lines: Vec<(u32, &'static str)> = vec![
+            //(0, "a drop on the"),
+            (0, "a surface"),
+            (0, "once untouched"),
+            (1, "by our storm."),
+            (0, "longing for"),
+            (1, "the art of"),
+            (2, "interference"),
         ];
         let line_count = text_lines.len();
+        let text_size = 1.75 * INCH;
+        let indent = TextBuilder::new()
+            .size(text_size)
+            .text_line("  ")
+            .measure()?
+            .width();
 
         let mut text_paths: Vec<Path> = vec![];
-        for (n, text_line) in text_lines.iter().enumerate().take(line_count) {
+        for (n, (indent_steps, text_line)) in text_lines.iter().enumerate() {
             let t = TextBuilder::new()
                 .origin(point(
-                    0.75 * INCH,
+                    0.75 * INCH + *indent_steps as f64 * indent,
                     (0.5 + self.text_y_offset) * INCH + 1.25 * INCH * n as f64,
                 ))
-                .size(1.75 * INCH)
+                .size(text_size)
                 .text_line(text_line)
                 .build()?;
 
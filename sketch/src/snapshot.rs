@@ -0,0 +1,447 @@
+use crate::sketch::*;
+use nightgraphics::prelude::{import_svg, Canvas, PathEl, Point, Shape, SvgRenderer, Vec2};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Decimal places coordinates are rounded to in normalized SVGs
+const PRECISION: usize = 3;
+
+/// Distance within which points are considered the same when diffing
+const TOLERANCE: f64 = 1e-2;
+
+/// Renders a sketch at its current parameters as a normalized SVG, suitable
+/// for comparing against a checked-in snapshot
+pub fn render_snapshot(sketch: &dyn Sketch) -> SketchResult<String> {
+    Ok(normalize_svg(&sketch.exec()?.render_svg_string()))
+}
+
+/// Attributes whose values are made up of coordinates and lengths
+const NUMERIC_ATTRIBUTES: &[&str] = &[
+    "cx",
+    "cy",
+    "d",
+    "height",
+    "points",
+    "r",
+    "rx",
+    "ry",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-width",
+    "transform",
+    "viewBox",
+    "width",
+    "x",
+    "x1",
+    "x2",
+    "y",
+    "y1",
+    "y2",
+];
+
+/// Rounds the numbers in an SVG document's coordinate and length
+/// attributes, so that floating point noise doesn't register as a change.
+/// Everything else, such as namespaces, ids and text, is left untouched.
+pub fn normalize_svg(svg: &str) -> String {
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |e| start + e + 1);
+        normalize_tag(&rest[start..end], &mut out);
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Appends a tag to `out`, rounding the values of its numeric attributes
+fn normalize_tag(tag: &str, out: &mut String) {
+    let mut rest = tag;
+    while let Some(equals) = rest.find("=\"") {
+        let name_start = rest[..equals]
+            .rfind(|c: char| c.is_whitespace())
+            .map_or(0, |i| i + 1);
+        let name = &rest[name_start..equals];
+        let value_start = equals + 2;
+        let value_end = match rest[value_start..].find('"') {
+            Some(e) => value_start + e,
+            None => break,
+        };
+        out.push_str(&rest[..value_start]);
+        let value = &rest[value_start..value_end];
+        if NUMERIC_ATTRIBUTES.contains(&name) {
+            normalize_numbers(value, out);
+        } else {
+            out.push_str(value);
+        }
+        rest = &rest[value_end..];
+    }
+    out.push_str(rest);
+}
+
+/// Appends `value` to `out` with every decimal number in it rounded
+fn normalize_numbers(value: &str, out: &mut String) {
+    let mut number = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let starts_number = c.is_ascii_digit()
+            || (c == '-' && matches!(chars.peek(), Some(n) if n.is_ascii_digit() || *n == '.'));
+        if !starts_number {
+            out.push(c);
+            continue;
+        }
+        number.clear();
+        number.push(c);
+        while let Some(&n) = chars.peek() {
+            if !n.is_ascii_digit() && (n != '.' || number.contains('.')) {
+                break;
+            }
+            number.push(n);
+            chars.next();
+        }
+        match number.parse::<f64>() {
+            Ok(value) if number.contains('.') => out.push_str(&format_number(value)),
+            _ => out.push_str(&number),
+        }
+    }
+}
+
+/// Formats a number rounded to `PRECISION` decimal places, without trailing
+/// zeros
+fn format_number(value: f64) -> String {
+    let formatted = format!("{:.*}", PRECISION, value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// A shape found in only one of two drawings, or in both at different
+/// positions
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeChange {
+    Added(ShapeSummary),
+    Removed(ShapeSummary),
+    Moved(ShapeSummary, Vec2),
+}
+
+/// Enough of a shape to recognize it by in a diff
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeSummary {
+    pub kind: &'static str,
+    pub pen: String,
+    pub start: Point,
+    pub end: Point,
+    /// Number of path elements the shape is drawn with
+    pub elements: usize,
+}
+
+impl fmt::Display for ShapeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} elements with pen '{}', from ({}, {}) to ({}, {})",
+            self.kind,
+            self.elements,
+            self.pen,
+            format_number(self.start.x),
+            format_number(self.start.y),
+            format_number(self.end.x),
+            format_number(self.end.y)
+        )
+    }
+}
+
+/// The geometric difference between two drawings
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    pub changes: Vec<ShapeChange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let count =
+            |kind: fn(&ShapeChange) -> bool| self.changes.iter().filter(|c| kind(c)).count();
+        writeln!(
+            f,
+            "{} added, {} removed, {} moved",
+            count(|c| matches!(c, ShapeChange::Added(_))),
+            count(|c| matches!(c, ShapeChange::Removed(_))),
+            count(|c| matches!(c, ShapeChange::Moved(..)))
+        )?;
+        for change in &self.changes {
+            match change {
+                ShapeChange::Added(s) => writeln!(f, "  + {}", s)?,
+                ShapeChange::Removed(s) => writeln!(f, "  - {}", s)?,
+                ShapeChange::Moved(s, by) => writeln!(
+                    f,
+                    "  ~ {}, moved by ({}, {})",
+                    s,
+                    format_number(by.x),
+                    format_number(by.y)
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares the shapes of two SVG documents, as written by `SvgRenderer`.
+///
+/// Shapes that appear in both, regardless of order, are ignored. Those that
+/// appear in both with every point offset by the same amount are reported as
+/// moved, and the rest as added to or removed from `expected`. Points within
+/// `TOLERANCE` of each other are considered the same.
+pub fn diff_svg(expected: &str, actual: &str) -> SketchResult<SnapshotDiff> {
+    let expected = shape_keys(&import_svg(expected)?);
+    let actual = shape_keys(&import_svg(actual)?);
+
+    // Shapes can only match others drawn with the same pen, stroke and
+    // sequence of elements, which are kept sorted by where they start
+    let mut unmatched: HashMap<&str, Vec<&ShapeKey>> = HashMap::new();
+    for key in &expected {
+        unmatched.entry(&key.structure).or_default().push(key);
+    }
+    for keys in unmatched.values_mut() {
+        keys.sort_by(|a, b| a.summary.start.x.total_cmp(&b.summary.start.x));
+    }
+    let mut added = vec![];
+    for key in &actual {
+        let candidates = match unmatched.get_mut(key.structure.as_str()) {
+            Some(candidates) => candidates,
+            None => {
+                added.push(key);
+                continue;
+            }
+        };
+        let x = key.summary.start.x;
+        let first = candidates.partition_point(|k| k.summary.start.x < x - TOLERANCE);
+        let found = candidates[first..]
+            .iter()
+            .take_while(|k| k.summary.start.x <= x + TOLERANCE)
+            .position(|k| same_points(&k.points, &key.points, Vec2::ZERO));
+        match found {
+            Some(n) => {
+                candidates.remove(first + n);
+            }
+            None => added.push(key),
+        }
+    }
+    let mut removed: Vec<&ShapeKey> = unmatched.into_values().flatten().collect();
+    removed.sort_by_key(|k| k.index);
+
+    let mut diff = SnapshotDiff::default();
+    for key in added {
+        let moved = removed.iter().position(|old| {
+            old.structure == key.structure
+                && same_points(
+                    &old.points,
+                    &key.points,
+                    key.summary.start - old.summary.start,
+                )
+        });
+        match moved {
+            Some(n) => {
+                let old = removed.remove(n);
+                diff.changes.push(ShapeChange::Moved(
+                    old.summary.clone(),
+                    key.summary.start - old.summary.start,
+                ))
+            }
+            None => diff.changes.push(ShapeChange::Added(key.summary.clone())),
+        }
+    }
+    diff.changes.extend(
+        removed
+            .into_iter()
+            .map(|k| ShapeChange::Removed(k.summary.clone())),
+    );
+    Ok(diff)
+}
+
+/// Returns whether each point of `b` is offset from its counterpart in `a` by
+/// `offset`, within `TOLERANCE`
+fn same_points(a: &[Point], b: &[Point], offset: Vec2) -> bool {
+    a.iter()
+        .zip(b)
+        .all(|(a, b)| (*b - *a - offset).hypot() <= TOLERANCE)
+}
+
+/// A shape's position in a drawing, along with the points of its path and a
+/// string identifying everything else about it
+struct ShapeKey {
+    index: usize,
+    summary: ShapeSummary,
+    structure: String,
+    points: Vec<Point>,
+}
+
+fn shape_keys(canvas: &Canvas) -> Vec<ShapeKey> {
+    let mut index = 0;
+    let mut keys = vec![];
+    for (pen, shapes) in canvas.layers() {
+        for shape in shapes {
+            let path = shape.to_path();
+            let elements = path.inner().elements();
+            let points: Vec<Point> = elements
+                .iter()
+                .flat_map(|el| match *el {
+                    PathEl::MoveTo(p) | PathEl::LineTo(p) => vec![p],
+                    PathEl::QuadTo(c, p) => vec![c, p],
+                    PathEl::CurveTo(c1, c2, p) => vec![c1, c2, p],
+                    PathEl::ClosePath => vec![],
+                })
+                .collect();
+            let (start, end) = match (points.first(), points.last()) {
+                (Some(start), Some(end)) => (*start, *end),
+                _ => continue,
+            };
+            let mut structure = format!("{}:{}:", pen.name, format_number(shape.stroke()));
+            structure.extend(elements.iter().map(|el| match el {
+                PathEl::MoveTo(_) => 'M',
+                PathEl::LineTo(_) => 'L',
+                PathEl::QuadTo(..) => 'Q',
+                PathEl::CurveTo(..) => 'C',
+                PathEl::ClosePath => 'Z',
+            }));
+            keys.push(ShapeKey {
+                index,
+                structure,
+                summary: ShapeSummary {
+                    kind: kind(&shape),
+                    pen: pen.name.clone(),
+                    start,
+                    end,
+                    elements: elements.len(),
+                },
+                points,
+            });
+            index += 1;
+        }
+    }
+    keys
+}
+
+fn kind(shape: &Shape) -> &'static str {
+    match shape {
+        Shape::Path(_) => "path",
+        Shape::Circle(_) => "circle",
+        Shape::Ellipse(_) => "ellipse",
+        Shape::Line(_) => "line",
+        Shape::Poly(_) => "polygon",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SketchList, CHARCOAL_FONT};
+    use nightgraphics::prelude::*;
+    use std::path::PathBuf;
+
+    /// Renders every sketch at its default parameters and compares it
+    /// against its snapshot in `snapshots/`. Run with `UPDATE_SNAPSHOTS=1`
+    /// set to write the current renderings as the new snapshots.
+    #[test]
+    fn sketches_match_snapshots() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots");
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        // Sketches that would read a font from the system look it up by a name
+        // of their own first, which gets a bundled font so that they render
+        // the same everywhere
+        let bundled = include_bytes!("../../assets/fonts/Jost-500-Medium.otf");
+        register_font(CHARCOAL_FONT, bundled.to_vec()).unwrap();
+        let mut failures = vec![];
+        for name in SketchList::sketch_names() {
+            let sketch = SketchList::sketch_by_name(&name).unwrap();
+            let actual = render_snapshot(sketch.as_ref())
+                .unwrap_or_else(|e| panic!("{} failed to render: {:?}", name, e));
+            let path = dir.join(format!("{}.svg", name.to_lowercase()));
+            if update {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, &actual).unwrap();
+                continue;
+            }
+            let expected = match std::fs::read_to_string(&path) {
+                Ok(svg) => svg,
+                Err(_) => {
+                    failures.push(format!("{} has no snapshot at {:?}", name, path));
+                    continue;
+                }
+            };
+            if normalize_svg(&expected) != actual {
+                // The shape diff explains most changes, but anything else in
+                // the document, such as its size or pen colors, fails too
+                let diff = diff_svg(&expected, &actual).unwrap();
+                let explanation = if diff.is_empty() {
+                    "the document changed outside of its shapes".to_string()
+                } else {
+                    diff.to_string()
+                };
+                failures.push(format!(
+                    "{} differs from its snapshot: {}",
+                    name, explanation
+                ));
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "{}\nRun with UPDATE_SNAPSHOTS=1 to accept the changes",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn normalizes_numbers() {
+        let svg = r##"<path d="M0.30000000000000004,-0.0000001 L12,3.14159" id="layer1" stroke="#000000"/>"##;
+        assert_eq!(
+            normalize_svg(svg),
+            r##"<path d="M0.3,0 L12,3.142" id="layer1" stroke="#000000"/>"##
+        );
+
+        // Numbers outside of coordinates and lengths are left alone
+        let svg = r##"<svg width="8.50000001in" xmlns="http://www.w3.org/2000/svg"><title>v1.2345</title></svg>"##;
+        assert_eq!(
+            normalize_svg(svg),
+            r##"<svg width="8.5in" xmlns="http://www.w3.org/2000/svg"><title>v1.2345</title></svg>"##
+        );
+    }
+
+    #[test]
+    fn diffs_shapes() {
+        let mut before = Canvas::new(point(0, 0), Size::new(100., 100.));
+        before.add(Circle::new(point(20, 20), 5.));
+        before.add(Line::new(point(0, 0), point(10, 10)).unwrap());
+        before.add(Line::new(point(50, 50), point(60, 50)).unwrap());
+
+        let mut after = Canvas::new(point(0, 0), Size::new(100., 100.));
+        after.add(Line::new(point(50, 50), point(60, 50)).unwrap());
+        after.add(Circle::new(point(25, 20), 5.));
+        after.add(Line::new(point(0, 0), point(10, 20)).unwrap());
+
+        let diff = diff_svg(&before.render_svg_string(), &after.render_svg_string()).unwrap();
+        assert_eq!(diff.changes.len(), 3);
+        assert!(matches!(
+            &diff.changes[0],
+            ShapeChange::Moved(s, by) if s.kind == "circle" && *by == Vec2::new(5., 0.)
+        ));
+        assert!(matches!(&diff.changes[1], ShapeChange::Added(s) if s.end == point(10, 20)));
+        assert!(matches!(&diff.changes[2], ShapeChange::Removed(s) if s.end == point(10, 10)));
+        assert!(diff
+            .to_string()
+            .starts_with("1 added, 1 removed, 1 moved\n  ~ circle"));
+
+        let same = diff_svg(&before.render_svg_string(), &before.render_svg_string()).unwrap();
+        assert!(same.is_empty());
+    }
+}
@@ -40,7 +40,8 @@ impl Default for Drawing {
             init: false,
             draw_debug_geom: false,
             draw_page_outline: false,
-            bg_color: Color32::from_rgb(30, 30, 30),
+            // Pens are drawn in their own colors, so default to a paper white
+            bg_color: Color32::from_rgb(245, 243, 235),
         }
     }
 }
@@ -140,7 +141,7 @@ impl Drawing {
             painter.add(EguiShape::rect_stroke(
                 to_screen.transform_rect(self.sketch_rect),
                 0.,
-                egui::Stroke::new(2., Color32::WHITE),
+                egui::Stroke::new(2., Color32::GRAY),
             ));
         }
 
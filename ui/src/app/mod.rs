@@ -137,6 +137,9 @@ impl eframe::App for NightgraphApp {
                     ui.collapsing("Sketch Settings", |ui| {
                         self.sketch_control.param_grid(ui);
                     });
+                    ui.collapsing("Plot Optimization", |ui| {
+                        self.sketch_control.optimize_grid(ui);
+                    });
                 });
                 if self.sketch_control.needs_render {
                     self.drawing.rerender(self.sketch_control.render().unwrap());
@@ -1,4 +1,5 @@
 use eframe::egui;
+use nightgraphics::optimize::{optimize, OptimizeSettings, OptimizeStats};
 use nightgraphics::render::EguiRenderer;
 use nightsketch::*;
 
@@ -7,6 +8,8 @@ pub struct SketchControl {
     sketch_name: String,
     params: Vec<ParamMetadata>,
     sketch_names: Vec<String>,
+    optimize: bool,
+    optimize_stats: Option<OptimizeStats>,
     pub needs_render: bool,
 }
 
@@ -21,6 +24,8 @@ impl Default for SketchControl {
             sketch_name,
             params,
             sketch_names,
+            optimize: false,
+            optimize_stats: None,
             needs_render: true,
         }
     }
@@ -150,7 +155,46 @@ impl SketchControl {
             .show(ui, |ui| self.param_grid_contents(ui));
     }
 
-    pub fn render(&self) -> SketchResult<(egui::Vec2, Vec<egui::Shape>)> {
-        Ok(self.sketch.exec()?.render_egui())
+    pub fn optimize_grid(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("optimize_grid")
+            .num_columns(2)
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Optimize paths");
+                if ui.checkbox(&mut self.optimize, "").changed() {
+                    self.needs_render = true;
+                }
+                ui.end_row();
+
+                if let Some(stats) = &self.optimize_stats {
+                    ui.label("Strokes");
+                    ui.label(format!(
+                        "{} → {}",
+                        stats.strokes_before, stats.strokes_after
+                    ));
+                    ui.end_row();
+                    ui.label("Pen down");
+                    ui.label(format!("{:.0}", stats.pen_down));
+                    ui.end_row();
+                    ui.label("Pen up");
+                    ui.label(format!(
+                        "{:.0} → {:.0}",
+                        stats.pen_up_before, stats.pen_up_after
+                    ));
+                    ui.end_row();
+                }
+            });
+    }
+
+    pub fn render(&mut self) -> SketchResult<(egui::Vec2, Vec<egui::Shape>)> {
+        let canvas = self.sketch.exec()?;
+        if self.optimize {
+            let (optimized, stats) = optimize(&canvas, &OptimizeSettings::default());
+            self.optimize_stats = Some(stats);
+            Ok(optimized.render_egui())
+        } else {
+            self.optimize_stats = None;
+            Ok(canvas.render_egui())
+        }
     }
 }